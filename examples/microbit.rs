@@ -17,6 +17,9 @@ use crate::cortex_m::interrupt::Mutex;
 use crate::cortex_m::peripheral::Peripherals;
 use cortex_m_rt::entry;
 
+use chirp::compat::{DelayCompat, I2cCompat};
+use chirp::supervisor::{Status, Supervisor};
+use embedded_hal::delay::DelayNs;
 use chirp::{Chirp, DEFAULT_ADDRESS};
 
 use core::cell::RefCell;
@@ -25,7 +28,7 @@ use core::ops::DerefMut;
 
 static GPIO: Mutex<RefCell<Option<GPIOTE>>> = Mutex::new(RefCell::new(None));
 static TX: Mutex<RefCell<Option<serial::Tx<UART0>>>> = Mutex::new(RefCell::new(None));
-static CHIRP: Mutex<RefCell<Option<Chirp<I2c<TWI1>>>>> = Mutex::new(RefCell::new(None));
+static CHIRP: Mutex<RefCell<Option<Chirp<I2cCompat<I2c<TWI1>>>>>> = Mutex::new(RefCell::new(None));
 
 #[entry]
 fn main() -> ! {
@@ -49,7 +52,7 @@ fn main() -> ! {
             let sda = gpio.pin30.into_open_drain_input().downgrade();
 
             // Set up I2C
-            let mut i2c = i2c::I2c::i2c1(p.TWI1, sda, scl);
+            let mut i2c = I2cCompat::new(i2c::I2c::i2c1(p.TWI1, sda, scl));
 
             let mut chirp = Chirp::new(i2c, chirp::DEFAULT_ADDRESS);
             
@@ -59,8 +62,7 @@ fn main() -> ! {
 
             // Change Chirp Sensor address
             // write!(&mut tx, "Change Address to 0x21");
-            // chirp.address(0x21);
-            // delay.delay_ms(100_u32);
+            // chirp.address(0x21, &mut chirp::compat::DelayCompat::new(delay));
             
             let version = match chirp.version() {
                     Result::Ok(version) => version,
@@ -73,49 +75,26 @@ fn main() -> ! {
                 };
             write!(&mut tx, "Version: {}\n\r", version);
             
-            loop {          
-                // Start messure the sensor so it's ready for reading
-                chirp.messure();
-                delay.delay_ms(1000_u32);
-
-                // Read Temperature
-                let temperature = match chirp.temperature() {
-                    Result::Ok(temperature) => temperature,
-                    Result::Err(error) => {
-                        write!(&mut tx, "Error: {:?}\n\r", error);
-                        loop {
-                            continue;
-                        };
+            // the supervisor restarts the sensor when it stops answering or its readings freeze,
+            // the loop keeps running even if the sensor is gone
+            let mut delay = DelayCompat::new(delay);
+            let mut supervisor = Supervisor::new(chirp);
+            loop {
+                match supervisor.poll(&mut delay) {
+                    Status::Reading(reading) => {
+                        write!(&mut tx, "Temperature: {}\n\r", reading.temperature);
+                        write!(&mut tx, "Capacitance: {}\n\r", reading.moisture);
+                        write!(&mut tx, "Light: {}\n\r", reading.light);
                     }
-                };
-                write!(&mut tx, "Temperature: {}\n\r", temperature);
-                delay.delay_ms(1000_u32);
-
-                // Read Capacitance
-                let capacitance = match chirp.capacitance() {
-                    Result::Ok(capacitance) => capacitance,
-                    Result::Err(error) => {
-                        write!(&mut tx, "Error: {:?}\n\r", error);
-                        loop {
-                            continue;
-                        };
+                    Status::Recovered(fault) => {
+                        write!(&mut tx, "Sensor restarted after {:?}\n\r", fault);
                     }
-                };
-                write!(&mut tx, "Capacitance: {}\n\r", capacitance);
-                delay.delay_ms(1000_u32);
-
-                // Read Light intensity
-                let light = match chirp.light() {
-                    Result::Ok(light) => light,
-                    Result::Err(error) => {
-                        write!(&mut tx, "Error: {:?}\n\r", error);
-                        loop {
-                            continue;
-                        };
+                    Status::Failed(fault, error) => {
+                        write!(&mut tx, "Sensor not responding after {:?}: {:?}\n\r", fault, error);
                     }
-                };
-                write!(&mut tx, "Light: {}\n\r", light);
-            }       
+                }
+                delay.delay_ms(1000);
+            }
             
         });
     }
@@ -20,6 +20,7 @@ use crate::cortex_m::interrupt::Mutex;
 use crate::cortex_m::peripheral::Peripherals;
 use cortex_m_rt::entry;
 
+use chirp::compat::I2cCompat;
 use chirp::{Chirp, DEFAULT_ADDRESS};
 
 use core::cell::RefCell;
@@ -28,7 +29,7 @@ use core::ops::DerefMut;
 
 static GPIO: Mutex<RefCell<Option<GPIOTE>>> = Mutex::new(RefCell::new(None));
 static TX: Mutex<RefCell<Option<serial::Tx<UART0>>>> = Mutex::new(RefCell::new(None));
-static CHIRP: Mutex<RefCell<Option<Chirp<I2c<TWI1>>>>> = Mutex::new(RefCell::new(None));
+static CHIRP: Mutex<RefCell<Option<Chirp<I2cCompat<I2c<TWI1>>>>>> = Mutex::new(RefCell::new(None));
 
 type LED = PIN<Output<PushPull>>;
 
@@ -551,7 +552,7 @@ fn main() -> ! {
             let sda = gpio.pin30.into_open_drain_input().downgrade();
 
             // Set up I2C
-            let mut i2c = i2c::I2c::i2c1(p.TWI1, sda, scl);
+            let mut i2c = I2cCompat::new(i2c::I2c::i2c1(p.TWI1, sda, scl));
 
             let mut chirp = Chirp::new(i2c, 0x23);
 
@@ -569,14 +570,9 @@ fn main() -> ! {
                     Result::Err(error) => {
                         write!(&mut tx, "Error: {:?}\n\r", error);
                         // scan all i2c devices
-                        for address in 0x01..0x7F {
-                            chirp = Chirp::new(chirp.destroy(), address);
-                            chirp.reset();
-                            delay.delay_ms(500_u32);
-                            let version = match chirp.version() {
-                                Result::Ok(version) => write!(&mut tx, "Info: 0x{:x} found version {}\n\r", address, version),
-                                Result::Err(error) => { write!(&mut tx, "Error: 0x{:x} {:?}\n\r", address, error); continue; }
-                            };
+                        let mut i2c = chirp.destroy();
+                        for device in chirp::discover(&mut i2c) {
+                            write!(&mut tx, "Info: 0x{:x} found version {}\n\r", device.address, device.firmware);
                         }
                         loop {
                             continue;
@@ -637,7 +633,7 @@ fn main() -> ! {
                         };
                     }
                 };
-                leds.display_temperature(&mut delay, temperature, 1500);
+                leds.display_temperature(&mut delay, temperature.to_f32(), 1500);
                 write!(&mut tx, "Temperature: {}\n\r", temperature);
                 // delay.delay_ms(1000_u32);
 
@@ -651,7 +647,7 @@ fn main() -> ! {
                         };
                     }
                 };
-                leds.display_capacitance(&mut delay, capacitance, 1500);
+                leds.display_capacitance(&mut delay, capacitance.raw(), 1500);
                 write!(&mut tx, "Capacitance: {}\n\r", capacitance);
                 // delay.delay_ms(1000_u32);
 
@@ -665,7 +661,7 @@ fn main() -> ! {
                         };
                     }
                 };
-                leds.display_light(&mut delay, light as u16, 1500);
+                leds.display_light(&mut delay, light.to_f32() as u16, 1500);
                 write!(&mut tx, "Light: {}\n\r", light);
                 
             }       
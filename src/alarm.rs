@@ -0,0 +1,186 @@
+// Threshold alarms on top of read_all(), what the chirp firmware itself does by chirping when the
+// plant is thirsty.
+//
+// Each condition has a threshold and a hysteresis. A condition becomes active when the reading
+// crosses the threshold and only recovers once the reading is back by more than the hysteresis, so
+// values jittering around a threshold don't flap. Only changes are reported, as events.
+//
+// Thresholds use the units of Reading: raw capacitance, tenths of a degree celsius and the raw
+// light count, which goes up when it gets darker.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use crate::{Chirp, Error, Reading};
+
+const CONDITIONS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    // moisture below threshold
+    Dry,
+    // moisture above threshold
+    Wet,
+    // temperature above threshold
+    Hot,
+    // temperature below threshold
+    Cold,
+    // light count above threshold
+    Dark,
+}
+
+impl Condition {
+    fn above(self) -> bool {
+        matches!(self, Condition::Wet | Condition::Hot | Condition::Dark)
+    }
+
+    fn value(self, reading: &Reading) -> i32 {
+        match self {
+            Condition::Dry | Condition::Wet => reading.moisture.raw() as i32,
+            Condition::Hot | Condition::Cold => reading.temperature.deci_celsius() as i32,
+            Condition::Dark => reading.light.raw() as i32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    BecameDry,
+    BecameWet,
+    BecameHot,
+    BecameCold,
+    BecameDark,
+    Recovered(Condition),
+}
+
+impl Event {
+    fn became(condition: Condition) -> Self {
+        match condition {
+            Condition::Dry => Event::BecameDry,
+            Condition::Wet => Event::BecameWet,
+            Condition::Hot => Event::BecameHot,
+            Condition::Cold => Event::BecameCold,
+            Condition::Dark => Event::BecameDark,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Threshold {
+    pub level: i32,
+    pub hysteresis: u32,
+}
+
+impl Threshold {
+    pub fn new(level: i32, hysteresis: u32) -> Self {
+        Threshold { level, hysteresis }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Alarm {
+    condition: Condition,
+    threshold: Option<Threshold>,
+    active: bool,
+}
+
+impl Alarm {
+    // new state if it changed
+    fn update(&mut self, reading: &Reading) -> Option<Event> {
+        let threshold = self.threshold?;
+        let value = self.condition.value(reading);
+        let hysteresis = threshold.hysteresis as i32;
+        let active = match (self.condition.above(), self.active) {
+            (true, false) => value > threshold.level,
+            (true, true) => value > threshold.level - hysteresis,
+            (false, false) => value < threshold.level,
+            (false, true) => value < threshold.level + hysteresis,
+        };
+        if active == self.active {
+            return None;
+        }
+        self.active = active;
+        Some(if active { Event::became(self.condition) } else { Event::Recovered(self.condition) })
+    }
+}
+
+// events of one update, in the order of Condition
+#[derive(Debug, Clone)]
+pub struct Events {
+    events: [Option<Event>; CONDITIONS],
+    next: usize,
+}
+
+impl Iterator for Events {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        while self.next < CONDITIONS {
+            self.next += 1;
+            if let Some(event) = self.events[self.next - 1] {
+                return Some(event);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Alarms {
+    alarms: [Alarm; CONDITIONS],
+}
+
+impl Alarms {
+    // no thresholds set, nothing is reported until one is
+    pub fn new() -> Self {
+        let alarm = |condition| Alarm { condition, threshold: None, active: false };
+        Alarms {
+            alarms: [
+                alarm(Condition::Dry),
+                alarm(Condition::Wet),
+                alarm(Condition::Hot),
+                alarm(Condition::Cold),
+                alarm(Condition::Dark),
+            ],
+        }
+    }
+
+    pub fn set_threshold(&mut self, condition: Condition, threshold: Option<Threshold>) {
+        let alarm = self.alarm(condition);
+        alarm.threshold = threshold;
+        alarm.active = false;
+    }
+
+    pub fn is_active(&self, condition: Condition) -> bool {
+        self.alarms.iter().any(|alarm| alarm.condition == condition && alarm.active)
+    }
+
+    pub fn update(&mut self, reading: &Reading) -> Events {
+        let mut events = [None; CONDITIONS];
+        for (event, alarm) in events.iter_mut().zip(self.alarms.iter_mut()) {
+            *event = alarm.update(reading);
+        }
+        Events { events, next: 0 }
+    }
+
+    // take a reading with Chirp::read_all() and update the alarms with it
+    pub fn check<I2C, E, D>(&mut self, chirp: &mut Chirp<I2C>, delay: &mut D) -> Result<Events, Error<E>>
+    where
+        I2C: I2c<Error = E>,
+        D: DelayNs,
+    {
+        let reading = chirp.read_all(delay)?;
+        Ok(self.update(&reading))
+    }
+
+    fn alarm(&mut self, condition: Condition) -> &mut Alarm {
+        // every condition has an alarm
+        self.alarms.iter_mut().find(|alarm| alarm.condition == condition).unwrap()
+    }
+}
+
+impl Default for Alarms {
+    fn default() -> Self {
+        Alarms::new()
+    }
+}
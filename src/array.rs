@@ -0,0 +1,111 @@
+// Several sensors sharing one bus.
+//
+// ChirpArray owns the bus, or borrows it when given a &mut to an I2c implementation, and talks to
+// each sensor by address. Light measurements are started on all sensors before waiting, so a sweep
+// over N sensors takes about as long as one measurement. Errors are reported per sensor, a failing
+// sensor doesn't stop the others.
+
+use core::array;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use crate::light::LightReading;
+use crate::units::{Moisture, Temperature};
+use crate::{Backoff, Chirp, Error, Reading, CAPACITANCE_SETTLE_MS, MEASUREMENT_TIMEOUT_MS};
+
+pub struct ChirpArray<I2C, const N: usize> {
+    i2c: I2C,
+    addresses: [u8; N],
+    backoff: Backoff,
+}
+
+impl<I2C, E, const N: usize> ChirpArray<I2C, N> where I2C: I2c<Error = E>, {
+    pub fn new(i2c: I2C, addresses: [u8; N]) -> Self {
+        ChirpArray { i2c, addresses, backoff: Backoff::default() }
+    }
+
+    pub fn destroy(self) -> I2C {
+        self.i2c
+    }
+
+    pub fn addresses(&self) -> &[u8; N] {
+        &self.addresses
+    }
+
+    // polling interval of read_all() while the light measurements run, also used by chirp()
+    pub fn set_backoff(&mut self, backoff: Backoff) {
+        self.backoff = backoff;
+    }
+
+    // driver for a single sensor of the array, panics if index is out of range
+    pub fn chirp(&mut self, index: usize) -> Chirp<&mut I2C> {
+        let mut chirp = Chirp::new(&mut self.i2c, self.addresses[index]);
+        chirp.set_backoff(self.backoff);
+        chirp
+    }
+
+    pub fn start_measurements(&mut self) -> [Result<(), Error<E>>; N] {
+        array::from_fn(|i| self.chirp(i).messure())
+    }
+
+    // read all channels of all sensors in one sweep, like Chirp::read_all() with the light
+    // measurements running in parallel
+    pub fn read_all<D: DelayNs>(&mut self, delay: &mut D) -> [Result<Reading, Error<E>>; N] {
+        // first capacitance read of old firmware returns the previous result, errors show up below
+        for i in 0..N {
+            let _ = self.chirp(i).capacitance();
+        }
+        delay.delay_ms(CAPACITANCE_SETTLE_MS);
+        let mut waited = CAPACITANCE_SETTLE_MS;
+
+        let mut channels = [(Moisture(0), Temperature(0)); N];
+        let mut readings: [Option<Result<Reading, Error<E>>>; N] = array::from_fn(|_| None);
+        for (i, (channel, reading)) in channels.iter_mut().zip(readings.iter_mut()).enumerate() {
+            match self.start(i) {
+                Ok(values) => *channel = values,
+                Err(error) => *reading = Some(Err(error)),
+            }
+        }
+
+        let timeout = waited + MEASUREMENT_TIMEOUT_MS;
+        let mut interval = self.backoff.initial_ms.max(1);
+        loop {
+            for (i, (&(moisture, temperature), reading)) in channels.iter().zip(readings.iter_mut()).enumerate() {
+                if reading.is_some() {
+                    continue;
+                }
+                match self.finish(i) {
+                    Ok(light) => *reading = Some(Ok(Reading { moisture, temperature, light, timestamp_hint: waited })),
+                    Err(nb::Error::Other(error)) => *reading = Some(Err(error)),
+                    Err(nb::Error::WouldBlock) => {}
+                }
+            }
+            if readings.iter().all(Option::is_some) || waited >= timeout {
+                break;
+            }
+            let step = interval.min(timeout - waited);
+            delay.delay_ms(step);
+            waited += step;
+            interval = self.backoff.next(interval);
+        }
+        readings.map(|reading| reading.unwrap_or(Err(Error::BusyTimeout)))
+    }
+
+    // read capacitance and temperature and start the light measurement
+    fn start(&mut self, index: usize) -> Result<(Moisture, Temperature), Error<E>> {
+        let mut chirp = self.chirp(index);
+        let moisture = chirp.capacitance()?;
+        let temperature = chirp.temperature()?;
+        chirp.messure()?;
+        Ok((moisture, temperature))
+    }
+
+    fn finish(&mut self, index: usize) -> nb::Result<LightReading, Error<E>> {
+        let mut chirp = self.chirp(index);
+        if chirp.busy()? {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(chirp.light_reading()?)
+    }
+}
@@ -0,0 +1,222 @@
+// Async variant of the driver on embedded-hal-async, for Embassy, RTIC and other executors.
+//
+// AsyncChirp offers the same readings as Chirp but awaits the bus and all waiting, so a light
+// measurement, which takes seconds, doesn't stall the executor.
+
+use embedded_hal_async::delay::DelayNs;
+use embedded_hal_async::i2c::I2c;
+
+use crate::calibration::Calibration;
+use crate::firmware::{self, Feature, FirmwareVersion};
+use crate::light::LightReading;
+use crate::trace::Tracer;
+use crate::units::{Moisture, MoisturePercent, Temperature};
+use crate::{
+    Backoff, Error, Reading, Register, CAPACITANCE_SETTLE_MS, MAX_ADDRESS, MEASUREMENT_TIMEOUT_MS, MIN_ADDRESS,
+    STARTUP_DELAY_MS, WAKE_DELAY_MS,
+};
+
+pub struct AsyncChirp<I2C> {
+    i2c: I2C,
+    address: u8,
+    firmware: Option<FirmwareVersion>,
+    backoff: Backoff,
+    tracer: Tracer,
+}
+
+impl<I2C, E> AsyncChirp<I2C> where I2C: I2c<Error = E>, {
+    pub fn new(i2c: I2C, address: u8) -> Self {
+        AsyncChirp { i2c, address, firmware: None, backoff: Backoff::default(), tracer: Tracer::default() }
+    }
+
+    pub fn destroy(self) -> I2C {
+        self.i2c
+    }
+
+    // see Chirp::init()
+    pub async fn init(&mut self) -> Result<FirmwareVersion, Error<E>> {
+        let firmware = firmware::check(self.version().await?)?;
+        self.firmware = Some(firmware);
+        Ok(firmware)
+    }
+
+    pub fn firmware(&self) -> Option<FirmwareVersion> {
+        self.firmware
+    }
+
+    pub fn set_backoff(&mut self, backoff: Backoff) {
+        self.backoff = backoff;
+    }
+
+    // monotonic microseconds, traced register accesses then log how long they took
+    #[cfg(any(feature = "defmt", feature = "log"))]
+    pub fn set_trace_clock(&mut self, clock: fn() -> u64) {
+        self.tracer.set_clock(clock);
+    }
+
+    // same flow as Chirp::address(), the previous address is kept on any failure and failures after
+    // the address was sent are reported as Error::AddressUnconfirmed
+    pub async fn address<D: DelayNs>(&mut self, address: u8, delay: &mut D) -> Result<(), Error<E>> {
+        if !(MIN_ADDRESS..=MAX_ADDRESS).contains(&address) {
+            return Err(Error::InvalidAddress(address));
+        }
+        if self.firmware.is_none() {
+            self.init().await?;
+        }
+        // the firmware only accepts the new address if the command is sent twice
+        for _ in 0..2 {
+            self.write_register(Register::ChirpAddress, Some(address)).await?;
+        }
+
+        let previous = self.address;
+        if self.confirm_address(address, delay).await.is_err() {
+            self.address = previous;
+            return Err(Error::AddressUnconfirmed { previous, address });
+        }
+        Ok(())
+    }
+
+    // reset still goes to the old address, the new address is used after reboot
+    async fn confirm_address<D: DelayNs>(&mut self, address: u8, delay: &mut D) -> Result<(), Error<E>> {
+        self.reset().await?;
+        delay.delay_ms(STARTUP_DELAY_MS).await;
+        self.address = address;
+        self.wait_until_idle(delay, MEASUREMENT_TIMEOUT_MS).await?;
+        self.verify_address().await
+    }
+
+    async fn verify_address(&mut self) -> Result<(), Error<E>> {
+        let (register, expected) = firmware::address_check(self.firmware, self.address);
+        let mut buffer = [0u8; 1];
+        self.read_register(register, &mut buffer).await?;
+        if buffer[0] != expected {
+            return Err(Error::UnexpectedValue { register: register as u8, expected, found: buffer[0] });
+        }
+        Ok(())
+    }
+
+    pub fn get_address(&self) -> u8 {
+        self.address
+    }
+
+    pub async fn reset(&mut self) -> Result<(), Error<E>> {
+        self.write_register(Register::ChirpReset, None).await
+    }
+
+    // reset and wait until the sensor is back up and idle
+    pub async fn restart<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<E>> {
+        self.reset().await?;
+        delay.delay_ms(STARTUP_DELAY_MS).await;
+        self.wait_until_idle(delay, MEASUREMENT_TIMEOUT_MS).await.map(|_| ())
+    }
+
+    pub async fn sleep(&mut self) -> Result<(), Error<E>> {
+        firmware::require(self.firmware, Feature::Sleep)?;
+        self.write_register(Register::ChirpSleep, None).await
+    }
+
+    // see Chirp::wake(), the first capacitance reading after waking is stale
+    pub async fn wake<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<E>> {
+        let _ = self.version().await;
+        delay.delay_ms(WAKE_DELAY_MS).await;
+        self.version().await.map(|_| ())
+    }
+
+    // start a light measurement and wait until the sensor has the new result
+    pub async fn measure_light<D: DelayNs>(&mut self, delay: &mut D) -> Result<LightReading, Error<E>> {
+        self.write_register(Register::ChirpLightMessure, None).await?;
+        self.wait_until_idle(delay, MEASUREMENT_TIMEOUT_MS).await?;
+        self.light_reading().await
+    }
+
+    // same sequence as Chirp::read_all()
+    pub async fn read_all<D: DelayNs>(&mut self, delay: &mut D) -> Result<Reading, Error<E>> {
+        let mut waited = 0;
+        if !firmware::known_to_support(self.firmware, Feature::CapacitanceFix) {
+            self.capacitance().await?;
+            delay.delay_ms(CAPACITANCE_SETTLE_MS).await;
+            waited += CAPACITANCE_SETTLE_MS;
+        }
+        let moisture = self.capacitance().await?;
+        let temperature = self.temperature().await?;
+
+        self.write_register(Register::ChirpLightMessure, None).await?;
+        waited += self.wait_until_idle(delay, MEASUREMENT_TIMEOUT_MS).await?;
+        let light = self.light_reading().await?;
+        Ok(Reading { moisture, temperature, light, timestamp_hint: waited })
+    }
+
+    pub async fn busy(&mut self) -> Result<bool, Error<E>> {
+        let mut buffer = [0u8; 1];
+        self.read_register(Register::ChirpBusy, &mut buffer).await?;
+        Ok(buffer[0] > 0)
+    }
+
+    pub async fn version(&mut self) -> Result<u8, Error<E>> {
+        let mut buffer = [0u8; 1];
+        self.read_register(Register::ChirpVersion, &mut buffer).await?;
+        Ok(buffer[0])
+    }
+
+    // result of the last light measurement without checking if it is finished
+    pub async fn light_reading(&mut self) -> Result<LightReading, Error<E>> {
+        let mut buffer = [0u8; 2];
+        self.read_register(Register::ChirpLight, &mut buffer).await?;
+        Ok(LightReading::new(u16::from_be_bytes(buffer)))
+    }
+
+    pub async fn temperature(&mut self) -> Result<Temperature, Error<E>> {
+        Ok(Temperature(self.temperature_raw().await?))
+    }
+
+    // temperature in tenths of a degree celsius
+    pub async fn temperature_raw(&mut self) -> Result<i16, Error<E>> {
+        let mut buffer = [0u8; 2];
+        self.read_register(Register::ChirpTemperature, &mut buffer).await?;
+        Ok(i16::from_be_bytes(buffer))
+    }
+
+    pub async fn capacitance(&mut self) -> Result<Moisture, Error<E>> {
+        let mut buffer = [0u8; 2];
+        self.read_register(Register::ChirpCapacitance, &mut buffer).await?;
+        Ok(Moisture(u16::from_be_bytes(buffer)))
+    }
+
+    // moisture according to the calibration of this sensor
+    pub async fn moisture(&mut self, calibration: &Calibration) -> Result<MoisturePercent, Error<E>> {
+        Ok(self.capacitance().await?.percent(calibration))
+    }
+
+    // same as Chirp::wait_until_idle()
+    pub async fn wait_until_idle<D: DelayNs>(&mut self, delay: &mut D, timeout_ms: u32) -> Result<u32, Error<E>> {
+        let mut waited = 0;
+        let mut interval = self.backoff.initial_ms.max(1);
+        while self.busy().await? {
+            if waited >= timeout_ms {
+                return Err(Error::BusyTimeout);
+            }
+            let step = interval.min(timeout_ms - waited);
+            delay.delay_ms(step).await;
+            waited += step;
+            interval = self.backoff.next(interval);
+        }
+        Ok(waited)
+    }
+
+    async fn write_register(&mut self, register: Register, value: Option<u8>) -> Result<(), Error<E>> {
+        let buffer = [register as u8, value.unwrap_or(0)];
+        let sent = if value.is_some() { &buffer[..] } else { &buffer[..1] };
+        let span = self.tracer.start(self.address, register, sent);
+        let result = self.i2c.write(self.address, sent).await;
+        self.tracer.finish::<I2C>(span, self.address, register, &result, &[]);
+        result.map_err(Error::I2c)
+    }
+
+    async fn read_register(&mut self, register: Register, buffer: &mut [u8]) -> Result<(), Error<E>> {
+        let sent = [register as u8];
+        let span = self.tracer.start(self.address, register, &sent);
+        let result = self.i2c.write_read(self.address, &sent, buffer).await;
+        self.tracer.finish::<I2C>(span, self.address, register, &result, buffer);
+        result.map_err(Error::I2c)
+    }
+}
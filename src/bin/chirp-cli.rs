@@ -0,0 +1,38 @@
+// chirp sensors on Linux hosts like the Raspberry Pi, see chirp::cli::USAGE
+//
+//     cargo run --target armv7-unknown-linux-gnueabihf --features std --bin chirp-cli -- --bus 1 read
+
+use std::env;
+use std::io;
+use std::process;
+
+use chirp::cli::{self, USAGE};
+use linux_embedded_hal::{Delay, I2cdev};
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--help" || arg == "-h") {
+        println!("{}", USAGE);
+        return;
+    }
+    let options = match cli::parse(&args) {
+        Ok(options) => options,
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+    let mut i2c = match I2cdev::new(&options.device) {
+        Ok(i2c) => i2c,
+        Err(error) => {
+            eprintln!("can't open {}: {}", options.device, error);
+            process::exit(1);
+        }
+    };
+    let mut delay = Delay;
+    let stdout = io::stdout();
+    if let Err(error) = cli::run(&options, &mut i2c, &mut delay, &mut stdout.lock()) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
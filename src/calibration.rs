@@ -0,0 +1,141 @@
+// Conversion of raw capacitance readings to moisture.
+//
+// A calibration is a curve of up to MAX_POINTS reference points, each mapping a capacitance to a
+// moisture in tenths of a percent. Between points the moisture is interpolated linearly, outside
+// the curve it is clamped to the first or last point. The simplest curve is a dry reading at 0 %
+// and a reading in saturated soil at 100 %, soil specific curves can map to volumetric water
+// content instead.
+
+pub const MAX_POINTS: usize = 8;
+
+// version, point count, points and checksum
+pub const SERIALIZED_LEN: usize = 2 + 4 * MAX_POINTS + 1;
+
+const FORMAT_VERSION: u8 = 1;
+
+// 100 % in tenths of a percent
+const MAX_MOISTURE: u16 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CalibrationError {
+    // a curve needs at least two points
+    TooFewPoints,
+    TooManyPoints,
+    // capacitance of the points has to increase strictly
+    NotIncreasing,
+    // moisture of a point is above 1000 tenths of a percent
+    MoistureOutOfRange,
+    // serialized data is truncated, of an unknown version or corrupted
+    InvalidData,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Point {
+    pub capacitance: u16,
+    // tenths of a percent
+    pub moisture: u16,
+}
+
+impl Point {
+    pub fn new(capacitance: u16, moisture: u16) -> Self {
+        Point { capacitance, moisture }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Calibration {
+    points: [Point; MAX_POINTS],
+    len: u8,
+}
+
+impl Calibration {
+    // reading in dry soil is 0 %, reading in saturated soil 100 %
+    pub fn new(dry: u16, wet: u16) -> Result<Self, CalibrationError> {
+        Calibration::from_points(&[Point::new(dry, 0), Point::new(wet, MAX_MOISTURE)])
+    }
+
+    pub fn from_points(points: &[Point]) -> Result<Self, CalibrationError> {
+        if points.len() < 2 {
+            return Err(CalibrationError::TooFewPoints);
+        }
+        if points.len() > MAX_POINTS {
+            return Err(CalibrationError::TooManyPoints);
+        }
+        if points.windows(2).any(|pair| pair[0].capacitance >= pair[1].capacitance) {
+            return Err(CalibrationError::NotIncreasing);
+        }
+        if points.iter().any(|point| point.moisture > MAX_MOISTURE) {
+            return Err(CalibrationError::MoistureOutOfRange);
+        }
+        let mut calibration = Calibration { points: [Point::default(); MAX_POINTS], len: points.len() as u8 };
+        calibration.points[..points.len()].copy_from_slice(points);
+        Ok(calibration)
+    }
+
+    pub fn points(&self) -> &[Point] {
+        &self.points[..self.len as usize]
+    }
+
+    // moisture in tenths of a percent
+    pub fn moisture(&self, capacitance: u16) -> u16 {
+        let points = self.points();
+        let first = points[0];
+        let last = points[points.len() - 1];
+        if capacitance <= first.capacitance {
+            return first.moisture;
+        }
+        if capacitance >= last.capacitance {
+            return last.moisture;
+        }
+        // there is always a segment containing the capacitance after the checks above
+        let segment = points
+            .windows(2)
+            .find(|pair| capacitance <= pair[1].capacitance)
+            .unwrap_or(&points[points.len() - 2..]);
+        let (low, high) = (segment[0], segment[1]);
+        let offset = (capacitance - low.capacitance) as i32;
+        let width = (high.capacitance - low.capacitance) as i32;
+        let span = high.moisture as i32 - low.moisture as i32;
+        (low.moisture as i32 + span * offset / width) as u16
+    }
+
+    #[cfg(feature = "float")]
+    pub fn moisture_percent(&self, capacitance: u16) -> f32 {
+        self.moisture(capacitance) as f32 / 10.0f32
+    }
+
+    // fixed size big endian encoding for EEPROM or flash
+    pub fn to_bytes(&self) -> [u8; SERIALIZED_LEN] {
+        let mut bytes = [0u8; SERIALIZED_LEN];
+        bytes[0] = FORMAT_VERSION;
+        bytes[1] = self.len;
+        for (chunk, point) in bytes[2..].chunks_mut(4).zip(self.points.iter()) {
+            chunk[..2].copy_from_slice(&point.capacitance.to_be_bytes());
+            chunk[2..4].copy_from_slice(&point.moisture.to_be_bytes());
+        }
+        bytes[SERIALIZED_LEN - 1] = checksum(&bytes[..SERIALIZED_LEN - 1]);
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, CalibrationError> {
+        if bytes.len() < SERIALIZED_LEN
+            || bytes[0] != FORMAT_VERSION
+            || bytes[1] as usize > MAX_POINTS
+            || bytes[SERIALIZED_LEN - 1] != checksum(&bytes[..SERIALIZED_LEN - 1])
+        {
+            return Err(CalibrationError::InvalidData);
+        }
+        let len = bytes[1] as usize;
+        let mut points = [Point::default(); MAX_POINTS];
+        for (point, chunk) in points.iter_mut().zip(bytes[2..].chunks(4)).take(len) {
+            point.capacitance = u16::from_be_bytes([chunk[0], chunk[1]]);
+            point.moisture = u16::from_be_bytes([chunk[2], chunk[3]]);
+        }
+        // a checksum can match by chance, stored points still have to form a valid curve
+        Calibration::from_points(&points[..len]).map_err(|_| CalibrationError::InvalidData)
+    }
+}
+
+fn checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) ^ 0xff
+}
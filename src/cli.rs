@@ -0,0 +1,197 @@
+// Command line front end of the chirp-cli binary.
+//
+// Parsing and running are kept apart from opening the device file, run() takes any I2c and DelayNs
+// so it works the same on /dev/i2c-N, the simulated sensors of sim and in tests.
+
+use std::fmt;
+use std::format;
+use std::io::{self, Write};
+use std::string::{String, ToString};
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use crate::supervisor::{Status, Supervisor};
+use crate::{discover, Chirp, Error, DEFAULT_ADDRESS};
+
+pub const USAGE: &str = "\
+usage: chirp-cli [--bus N | --device PATH] [--address ADDRESS] COMMAND
+
+commands:
+    read                    read moisture, temperature and light once
+    scan                    list the chirp sensors on the bus
+    set-address ADDRESS     move the sensor to a new address
+    reset                   restart the sensor
+    version                 print the firmware version
+    watch [--interval MS] [--count N]
+                            read every MS milliseconds, restarting hung sensors
+
+options:
+    --bus N                 use /dev/i2c-N, default 1
+    --device PATH           use the i2c device file at PATH
+    --address ADDRESS       address of the sensor, default 0x20
+
+addresses are decimal or hex with 0x";
+
+// milliseconds between reads of watch
+pub const WATCH_INTERVAL_MS: u32 = 1000;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub device: String,
+    pub address: u8,
+    pub command: Command,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Read,
+    Scan,
+    SetAddress(u8),
+    Reset,
+    Version,
+    // count None watches forever
+    Watch { interval_ms: u32, count: Option<u32> },
+}
+
+#[derive(Debug)]
+pub enum CliError<E> {
+    Chirp(Error<E>),
+    Io(io::Error),
+}
+
+impl<E> From<Error<E>> for CliError<E> {
+    fn from(error: Error<E>) -> Self {
+        CliError::Chirp(error)
+    }
+}
+
+impl<E> From<io::Error> for CliError<E> {
+    fn from(error: io::Error) -> Self {
+        CliError::Io(error)
+    }
+}
+
+impl<E: fmt::Debug> fmt::Display for CliError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Chirp(error) => write!(f, "sensor error: {:?}", error),
+            CliError::Io(error) => write!(f, "output error: {}", error),
+        }
+    }
+}
+
+// arguments without the program name
+pub fn parse<S: AsRef<str>>(args: &[S]) -> Result<Options, String> {
+    let mut device = String::from("/dev/i2c-1");
+    let mut address = DEFAULT_ADDRESS;
+    let mut args = args.iter().map(AsRef::as_ref);
+    let command = loop {
+        match args.next() {
+            Some("--bus") => device = format!("/dev/i2c-{}", number::<u8>(value(&mut args, "--bus")?)?),
+            Some("--device") => device = value(&mut args, "--device")?.to_string(),
+            Some("--address") => address = parse_address(value(&mut args, "--address")?)?,
+            Some(command) => break command,
+            None => return Err("missing command".to_string()),
+        }
+    };
+    let command = match command {
+        "read" => Command::Read,
+        "scan" => Command::Scan,
+        "set-address" => Command::SetAddress(parse_address(value(&mut args, "set-address")?)?),
+        "reset" => Command::Reset,
+        "version" => Command::Version,
+        "watch" => {
+            let mut interval_ms = WATCH_INTERVAL_MS;
+            let mut count = None;
+            loop {
+                match args.next() {
+                    Some("--interval") => interval_ms = number(value(&mut args, "--interval")?)?,
+                    Some("--count") => count = Some(number(value(&mut args, "--count")?)?),
+                    Some(arg) => return Err(format!("unexpected argument {}", arg)),
+                    None => break,
+                }
+            }
+            Command::Watch { interval_ms, count }
+        }
+        command => return Err(format!("unknown command {}", command)),
+    };
+    if let Some(arg) = args.next() {
+        return Err(format!("unexpected argument {}", arg));
+    }
+    Ok(Options { device, address, command })
+}
+
+pub fn run<I2C, E, D, W>(options: &Options, i2c: &mut I2C, delay: &mut D, out: &mut W) -> Result<(), CliError<E>>
+where
+    I2C: I2c<Error = E>,
+    E: fmt::Debug,
+    D: DelayNs,
+    W: Write,
+{
+    match options.command {
+        Command::Scan => {
+            for device in discover(i2c) {
+                writeln!(out, "0x{:02x} firmware {}", device.address, device.firmware)?;
+            }
+        }
+        Command::Version => {
+            let firmware = Chirp::new(i2c, options.address).init()?;
+            writeln!(out, "{}", firmware)?;
+        }
+        Command::Reset => {
+            Chirp::new(i2c, options.address).restart(delay)?;
+            writeln!(out, "reset 0x{:02x}", options.address)?;
+        }
+        Command::SetAddress(address) => {
+            let mut chirp = Chirp::new(i2c, options.address);
+            chirp.init()?;
+            chirp.address(address, delay)?;
+            writeln!(out, "moved 0x{:02x} to 0x{:02x}", options.address, address)?;
+        }
+        Command::Read => {
+            let mut chirp = Chirp::new(i2c, options.address);
+            chirp.init()?;
+            let reading = chirp.read_all(delay)?;
+            writeln!(out, "moisture: {}", reading.moisture)?;
+            writeln!(out, "temperature: {}", reading.temperature)?;
+            writeln!(out, "light: {}", reading.light)?;
+        }
+        Command::Watch { interval_ms, count } => {
+            let mut chirp = Chirp::new(i2c, options.address);
+            chirp.init()?;
+            let mut supervisor = Supervisor::new(chirp);
+            let mut polls = 0;
+            while count != Some(polls) {
+                match supervisor.poll(delay) {
+                    Status::Reading(reading) => writeln!(
+                        out,
+                        "moisture: {} temperature: {} light: {}",
+                        reading.moisture, reading.temperature, reading.light
+                    )?,
+                    Status::Recovered(fault) => writeln!(out, "restarted sensor after {:?}", fault)?,
+                    Status::Failed(fault, error) => writeln!(out, "sensor not responding after {:?}: {:?}", fault, error)?,
+                }
+                polls += 1;
+                delay.delay_ms(interval_ms);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn value<'a>(args: &mut impl Iterator<Item = &'a str>, name: &str) -> Result<&'a str, String> {
+    args.next().ok_or_else(|| format!("{} needs a value", name))
+}
+
+fn number<T: core::str::FromStr>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid number {}", value))
+}
+
+fn parse_address(value: &str) -> Result<u8, String> {
+    let address = match value.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => value.parse().ok(),
+    };
+    address.ok_or_else(|| format!("invalid address {}", value))
+}
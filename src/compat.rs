@@ -0,0 +1,84 @@
+// Adapters to use the driver with HALs that still implement the embedded-hal 0.2 traits.
+//
+// The driver itself is built on the embedded-hal 1.0 I2c and DelayNs traits. Wrap a 0.2 bus in
+// I2cCompat and a 0.2 delay in DelayCompat:
+//
+//     let mut chirp = Chirp::new(I2cCompat::new(i2c), DEFAULT_ADDRESS);
+//     chirp.address(0x21, &mut DelayCompat::new(delay))?;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, Operation, SevenBitAddress};
+use embedded_hal_02::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal_02::blocking::i2c::{Write, WriteRead};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompatError<E> {
+    I2c(E),
+    // 0.2 buses only offer write and write_read, other transaction shapes can't be mapped
+    Unsupported,
+}
+
+impl<E: core::fmt::Debug> embedded_hal::i2c::Error for CompatError<E> {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::Other
+    }
+}
+
+pub struct I2cCompat<I2C> {
+    i2c: I2C,
+}
+
+impl<I2C> I2cCompat<I2C> {
+    pub fn new(i2c: I2C) -> Self {
+        I2cCompat { i2c }
+    }
+
+    pub fn destroy(self) -> I2C {
+        self.i2c
+    }
+}
+
+impl<I2C, E> ErrorType for I2cCompat<I2C> where I2C: WriteRead<Error = E> + Write<Error = E>, E: core::fmt::Debug, {
+    type Error = CompatError<E>;
+}
+
+impl<I2C, E> I2c for I2cCompat<I2C> where I2C: WriteRead<Error = E> + Write<Error = E>, E: core::fmt::Debug, {
+    fn transaction(&mut self, address: SevenBitAddress, operations: &mut [Operation<'_>]) -> Result<(), Self::Error> {
+        match operations {
+            [Operation::Write(bytes)] => self.i2c.write(address, bytes).map_err(CompatError::I2c),
+            [Operation::Write(bytes), Operation::Read(buffer)] => {
+                self.i2c.write_read(address, bytes, buffer).map_err(CompatError::I2c)
+            }
+            _ => Err(CompatError::Unsupported),
+        }
+    }
+}
+
+pub struct DelayCompat<D> {
+    delay: D,
+}
+
+impl<D> DelayCompat<D> {
+    pub fn new(delay: D) -> Self {
+        DelayCompat { delay }
+    }
+
+    pub fn destroy(self) -> D {
+        self.delay
+    }
+}
+
+impl<D> DelayNs for DelayCompat<D> where D: DelayUs<u32> + DelayMs<u32>, {
+    fn delay_ns(&mut self, ns: u32) {
+        // 0.2 delays have microsecond resolution at best, round up
+        self.delay.delay_us(ns.div_ceil(1000));
+    }
+
+    fn delay_us(&mut self, us: u32) {
+        self.delay.delay_us(us);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.delay.delay_ms(ms);
+    }
+}
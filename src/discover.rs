@@ -0,0 +1,70 @@
+// Scan a bus for chirp sensors.
+//
+// Every address is first probed with an address only write, devices acknowledging it get their
+// version register read and those answering with a plausible version are then checked through the
+// get address register if their firmware has it. Other devices on the bus only see the register
+// pointer of the version read, no data is written to them.
+
+use core::ops::RangeInclusive;
+
+use embedded_hal::i2c::I2c;
+
+use crate::firmware::{self, Feature, FirmwareVersion};
+use crate::{Register, MAX_ADDRESS, MIN_ADDRESS};
+
+// every address a sensor can be moved to
+pub const SCAN_ADDRESSES: RangeInclusive<u8> = MIN_ADDRESS..=MAX_ADDRESS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Device {
+    pub address: u8,
+    pub firmware: FirmwareVersion,
+}
+
+pub struct Discover<'a, I2C> {
+    i2c: &'a mut I2C,
+    addresses: RangeInclusive<u8>,
+}
+
+// chirp sensors in SCAN_ADDRESSES, probed lazily while iterating
+pub fn discover<I2C: I2c>(i2c: &mut I2C) -> Discover<'_, I2C> {
+    discover_in(i2c, SCAN_ADDRESSES)
+}
+
+pub fn discover_in<I2C: I2c>(i2c: &mut I2C, addresses: RangeInclusive<u8>) -> Discover<'_, I2C> {
+    Discover { i2c, addresses }
+}
+
+impl<'a, I2C: I2c> Discover<'a, I2C> {
+    fn probe(&mut self, address: u8) -> Option<Device> {
+        // nothing at the address, skip it without writing a register pointer
+        self.i2c.write(address, &[]).ok()?;
+        let mut buffer = [0u8; 1];
+        self.i2c.write_read(address, &[Register::ChirpVersion as u8], &mut buffer).ok()?;
+        let firmware = FirmwareVersion::from_register(buffer[0]);
+        // any other device answering with some byte
+        if !firmware::plausible(firmware) {
+            return None;
+        }
+        if firmware.supports(Feature::AddressReadback) {
+            self.i2c.write_read(address, &[Register::ChirpGetAddress as u8], &mut buffer).ok()?;
+            if buffer[0] != address {
+                return None;
+            }
+        }
+        Some(Device { address, firmware })
+    }
+}
+
+impl<'a, I2C: I2c> Iterator for Discover<'a, I2C> {
+    type Item = Device;
+
+    fn next(&mut self) -> Option<Device> {
+        while let Some(address) = self.addresses.next() {
+            if let Some(device) = self.probe(address) {
+                return Some(device);
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,181 @@
+// Smoothing of noisy readings without allocation.
+//
+// FilteredChirp keeps the last N samples of each channel in a ring buffer and returns them
+// filtered. Samples too far from the median of the buffer are discarded as outliers, unless N of
+// them arrive in a row, then the value really changed and the buffer starts over. The first
+// capacitance read after new() or reset() returns garbage and is always dropped.
+
+use embedded_hal::i2c::I2c;
+
+use crate::light::LightReading;
+use crate::units::{Moisture, Temperature};
+use crate::{Chirp, Error};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    MovingAverage,
+    Median,
+    // new = old + (sample - old) * percent / 100
+    Exponential { percent: u8 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Channel<const N: usize> {
+    samples: [i32; N],
+    len: usize,
+    next: usize,
+    filter: Filter,
+    // exponential average in hundredths, so steps smaller than a count still add up
+    smoothed: Option<i64>,
+    // samples further away from the median are outliers
+    max_deviation: Option<u32>,
+    rejected: usize,
+}
+
+impl<const N: usize> Channel<N> {
+    pub fn new(filter: Filter) -> Self {
+        Channel { samples: [0; N], len: 0, next: 0, filter, smoothed: None, max_deviation: None, rejected: 0 }
+    }
+
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    pub fn set_max_deviation(&mut self, max_deviation: Option<u32>) {
+        self.max_deviation = max_deviation;
+    }
+
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.next = 0;
+        self.smoothed = None;
+        self.rejected = 0;
+    }
+
+    pub fn samples(&self) -> &[i32] {
+        &self.samples[..self.len]
+    }
+
+    // add a sample, false if it was discarded as outlier
+    pub fn push(&mut self, sample: i32) -> bool {
+        if N == 0 {
+            return false;
+        }
+        if let (Some(max_deviation), Some(median)) = (self.max_deviation, self.median()) {
+            if (sample - median).unsigned_abs() > max_deviation {
+                self.rejected += 1;
+                if self.rejected < N {
+                    return false;
+                }
+                self.clear();
+            }
+        }
+        self.rejected = 0;
+        self.samples[self.next] = sample;
+        self.next = (self.next + 1) % N;
+        self.len = (self.len + 1).min(N);
+        self.smoothed = Some(match (self.smoothed, self.filter) {
+            (Some(old), Filter::Exponential { percent }) => old + (sample as i64 * 100 - old) * percent.min(100) as i64 / 100,
+            _ => sample as i64 * 100,
+        });
+        true
+    }
+
+    // filtered value, None before the first sample
+    pub fn value(&self) -> Option<i32> {
+        match self.filter {
+            Filter::MovingAverage if self.len > 0 => {
+                let sum: i64 = self.samples().iter().map(|&sample| sample as i64).sum();
+                Some((sum / self.len as i64) as i32)
+            }
+            Filter::MovingAverage => None,
+            Filter::Median => self.median(),
+            Filter::Exponential { .. } => self.smoothed.map(|smoothed| (smoothed + 50).div_euclid(100) as i32),
+        }
+    }
+
+    fn median(&self) -> Option<i32> {
+        if self.len == 0 {
+            return None;
+        }
+        let mut sorted = self.samples;
+        let sorted = &mut sorted[..self.len];
+        sorted.sort_unstable();
+        Some(sorted[self.len / 2])
+    }
+}
+
+pub struct FilteredChirp<I2C, const N: usize> {
+    chirp: Chirp<I2C>,
+    capacitance: Channel<N>,
+    temperature: Channel<N>,
+    light: Channel<N>,
+    // next capacitance read is garbage
+    discard_capacitance: bool,
+}
+
+impl<I2C, E, const N: usize> FilteredChirp<I2C, N> where I2C: I2c<Error = E>, {
+    pub fn new(chirp: Chirp<I2C>, filter: Filter) -> Self {
+        FilteredChirp {
+            chirp,
+            capacitance: Channel::new(filter),
+            temperature: Channel::new(filter),
+            light: Channel::new(filter),
+            discard_capacitance: true,
+        }
+    }
+
+    pub fn destroy(self) -> Chirp<I2C> {
+        self.chirp
+    }
+
+    // unfiltered access to the sensor
+    pub fn chirp(&mut self) -> &mut Chirp<I2C> {
+        &mut self.chirp
+    }
+
+    pub fn capacitance_channel(&mut self) -> &mut Channel<N> {
+        &mut self.capacitance
+    }
+
+    pub fn temperature_channel(&mut self) -> &mut Channel<N> {
+        &mut self.temperature
+    }
+
+    pub fn light_channel(&mut self) -> &mut Channel<N> {
+        &mut self.light
+    }
+
+    // reset the sensor and start all channels over
+    pub fn reset(&mut self) -> Result<(), Error<E>> {
+        self.chirp.reset()?;
+        self.capacitance.clear();
+        self.temperature.clear();
+        self.light.clear();
+        self.discard_capacitance = true;
+        Ok(())
+    }
+
+    pub fn capacitance(&mut self) -> Result<Moisture, Error<E>> {
+        if self.discard_capacitance {
+            self.chirp.capacitance()?;
+            self.discard_capacitance = false;
+        }
+        let sample = self.chirp.capacitance()?.raw();
+        self.capacitance.push(sample as i32);
+        Ok(Moisture(self.capacitance.value().map_or(sample, |value| value as u16)))
+    }
+
+    pub fn temperature(&mut self) -> Result<Temperature, Error<E>> {
+        let sample = self.chirp.temperature()?.deci_celsius();
+        self.temperature.push(sample as i32);
+        Ok(Temperature(self.temperature.value().map_or(sample, |value| value as i16)))
+    }
+
+    // filtered result of the last light measurements
+    pub fn light_reading(&mut self) -> Result<LightReading, Error<E>> {
+        let sample = self.chirp.light_reading()?.raw();
+        self.light.push(sample as i32);
+        Ok(LightReading::new(self.light.value().map_or(sample, |value| value as u16)))
+    }
+}
@@ -0,0 +1,100 @@
+// Firmware versions and the features they support.
+//
+// The version register holds the version as two nibbles, 0x26 means 2.6. Features and the first
+// version that has them:
+//
+// - Sleep: sleep register 0x08, since 2.3
+// - AddressReadback: get address register 0x02, since 2.4
+// - CapacitanceFix: capacitance reads return a fresh value instead of the previous one, since 2.7
+
+use core::fmt;
+
+use crate::{Error, Register};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FirmwareVersion {
+    pub major: u8,
+    pub minor: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+    Sleep,
+    AddressReadback,
+    CapacitanceFix,
+}
+
+impl Feature {
+    pub fn since(self) -> FirmwareVersion {
+        match self {
+            Feature::Sleep => FirmwareVersion::new(2, 3),
+            Feature::AddressReadback => FirmwareVersion::new(2, 4),
+            Feature::CapacitanceFix => FirmwareVersion::new(2, 7),
+        }
+    }
+}
+
+impl FirmwareVersion {
+    // oldest firmware the driver talks to
+    pub const MIN_SUPPORTED: FirmwareVersion = FirmwareVersion::new(2, 2);
+
+    pub const fn new(major: u8, minor: u8) -> Self {
+        FirmwareVersion { major, minor }
+    }
+
+    // decode the value of the version register
+    pub fn from_register(value: u8) -> Self {
+        FirmwareVersion::new(value >> 4, value & 0x0f)
+    }
+
+    pub fn to_register(self) -> u8 {
+        self.major << 4 | self.minor & 0x0f
+    }
+
+    pub fn supports(self, feature: Feature) -> bool {
+        self >= feature.since()
+    }
+}
+
+impl fmt::Display for FirmwareVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+// decode the version register and refuse firmware older than MIN_SUPPORTED
+pub(crate) fn check<E>(value: u8) -> Result<FirmwareVersion, Error<E>> {
+    let firmware = FirmwareVersion::from_register(value);
+    if firmware < FirmwareVersion::MIN_SUPPORTED {
+        return Err(Error::UnsupportedFirmware(firmware));
+    }
+    Ok(firmware)
+}
+
+// version a chirp can report, anything else comes from another device or from several devices
+// answering at once
+pub(crate) fn plausible(firmware: FirmwareVersion) -> bool {
+    firmware.major == 2 && firmware >= FirmwareVersion::MIN_SUPPORTED && firmware.minor <= 9
+}
+
+// unknown firmware (no init()) is allowed to try
+pub(crate) fn require<E>(firmware: Option<FirmwareVersion>, feature: Feature) -> Result<(), Error<E>> {
+    match firmware {
+        Some(firmware) if !firmware.supports(feature) => Err(Error::UnsupportedFirmware(firmware)),
+        _ => Ok(()),
+    }
+}
+
+// unknown firmware (no init()) needs the workarounds for old firmware
+pub(crate) fn known_to_support(firmware: Option<FirmwareVersion>, feature: Feature) -> bool {
+    matches!(firmware, Some(firmware) if firmware.supports(feature))
+}
+
+// register and value to read back after an address change, firmware without the get address
+// register is checked by reading its version at the new address instead
+pub(crate) fn address_check(firmware: Option<FirmwareVersion>, address: u8) -> (Register, u8) {
+    match firmware {
+        Some(firmware) if !firmware.supports(Feature::AddressReadback) => (Register::ChirpVersion, firmware.to_register()),
+        _ => (Register::ChirpGetAddress, address),
+    }
+}
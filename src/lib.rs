@@ -1,93 +1,420 @@
 #![deny(warnings)]
 #![no_std]
 
+#[cfg(feature = "std")]
+extern crate std;
+
 extern crate embedded_hal as hal;
 
-use embedded_hal::blocking::i2c::{Write, WriteRead};
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+pub mod alarm;
+mod array;
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod calibration;
+#[cfg(feature = "std")]
+pub mod cli;
+#[cfg(feature = "eh02")]
+pub mod compat;
+mod discover;
+pub mod filter;
+pub mod firmware;
+pub mod light;
+pub mod provision;
+pub mod schedule;
+pub mod sim;
+pub mod supervisor;
+mod trace;
+pub mod units;
+
+pub use array::ChirpArray;
+pub use discover::{discover, discover_in, Device, Discover, SCAN_ADDRESSES};
+
+use calibration::Calibration;
+use firmware::{Feature, FirmwareVersion};
+use light::LightReading;
+use trace::Tracer;
+use units::{Moisture, MoisturePercent, Temperature};
 
 pub const DEFAULT_ADDRESS: u8 = 0x20;
 
+// lowest and highest address a sensor can be moved to, the addresses outside are reserved by the
+// I2C specification and not scanned by discover()
+pub const MIN_ADDRESS: u8 = 0x08;
+pub const MAX_ADDRESS: u8 = 0x77;
+
+// time the sensor needs after a reset before it answers on the bus again
+pub const STARTUP_DELAY_MS: u32 = 1000;
+
+// time the sensor needs after the wake up transaction before it answers again
+pub const WAKE_DELAY_MS: u32 = 20;
+
+// a light measurement takes about 3 seconds, give up after MEASUREMENT_TIMEOUT_MS
+pub const MEASUREMENT_TIMEOUT_MS: u32 = 5000;
+pub const BUSY_POLL_MS: u32 = 100;
+
+// how long to wait between polls of the busy register, starts at initial_ms and is multiplied by
+// factor after every poll up to max_ms
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Backoff {
+    pub initial_ms: u32,
+    pub max_ms: u32,
+    pub factor: u32,
+}
+
+impl Backoff {
+    pub const fn fixed(interval_ms: u32) -> Self {
+        Backoff { initial_ms: interval_ms, max_ms: interval_ms, factor: 1 }
+    }
+
+    // doubling from initial_ms up to max_ms
+    pub const fn exponential(initial_ms: u32, max_ms: u32) -> Self {
+        Backoff { initial_ms, max_ms, factor: 2 }
+    }
+
+    fn next(&self, interval_ms: u32) -> u32 {
+        interval_ms.saturating_mul(self.factor).min(self.max_ms).max(1)
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff::fixed(BUSY_POLL_MS)
+    }
+}
+
+// the capacitance register returns the result of the previous read, wait this long before reading
+// it again to get a fresh value
+pub const CAPACITANCE_SETTLE_MS: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error<E> {
+    // error from the underlying I2C bus (NACK, arbitration lost, ...)
+    I2c(E),
+    // address is not a valid 7-bit I2C address
+    InvalidAddress(u8),
+    // sensor did not become idle in time
+    BusyTimeout,
+    // register returned something other than expected
+    UnexpectedValue { register: u8, expected: u8, found: u8 },
+    // operation is not supported by the firmware version of the sensor
+    UnsupportedFirmware(FirmwareVersion),
+    // the address change was sent but the sensor couldn't be confirmed at address afterwards, it
+    // may answer at previous or already at address. The driver keeps talking to previous
+    AddressUnconfirmed { previous: u8, address: u8 },
+    // read_light() without a pending measurement of this sensor, its result was already read
+    NoMeasurement,
+}
+
+#[derive(Clone, Copy)]
 enum Register {
     ChirpCapacitance = 0x00, // result: u16
     ChirpAddress = 0x01, // set new address
-    //ChirpAddress = 0x02, // get address
+    ChirpGetAddress = 0x02, // get address
     ChirpLightMessure = 0x03, // write: u8
     ChirpLight = 0x04, // result: u16
     ChirpTemperature = 0x05, // result: i16 / 10 (float)
     ChirpReset = 0x06, // write: u8
     ChirpVersion = 0x07,
-    //ChirpSleep = 0x08,
+    ChirpSleep = 0x08, // write: u8
     ChirpBusy = 0x09, // result u8 (1 = busy, 0 = idle)
 }
+
+impl Register {
+    #[cfg(any(feature = "defmt", feature = "log"))]
+    fn name(self) -> &'static str {
+        match self {
+            Register::ChirpCapacitance => "capacitance",
+            Register::ChirpAddress => "set address",
+            Register::ChirpGetAddress => "get address",
+            Register::ChirpLightMessure => "measure light",
+            Register::ChirpLight => "light",
+            Register::ChirpTemperature => "temperature",
+            Register::ChirpReset => "reset",
+            Register::ChirpVersion => "version",
+            Register::ChirpSleep => "sleep",
+            Register::ChirpBusy => "busy",
+        }
+    }
+}
+// snapshot of all channels taken by read_all()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reading {
+    pub moisture: Moisture,
+    pub temperature: Temperature,
+    pub light: LightReading,
+    // milliseconds read_all() waited, moisture and temperature are about that much older than light
+    pub timestamp_hint: u32,
+}
+
+// light measurement started with start_measurement(), pass it to read_light() to get the result
+#[must_use]
+#[derive(Debug, PartialEq, Eq)]
+pub struct Measurement {
+    address: u8,
+}
+
 pub struct Chirp<I2C> {
     i2c: I2C,
     address: u8,
+    // probed by init(), without it nothing is refused and workarounds for old firmware are used
+    firmware: Option<FirmwareVersion>,
+    backoff: Backoff,
+    tracer: Tracer,
+    // started by start_measurement() and not read yet
+    measuring: bool,
 }
 
-impl<I2C, E> Chirp<I2C> where I2C: WriteRead<Error = E> + Write<Error = E>, {
+impl<I2C, E> Chirp<I2C> where I2C: I2c<Error = E>, {
     pub fn new(i2c: I2C, address: u8) -> Self {
-        Chirp { i2c, address }
+        Chirp { i2c, address, firmware: None, backoff: Backoff::default(), tracer: Tracer::default(), measuring: false }
     }
     pub fn destroy(self) -> I2C {
         self.i2c
     }
 
-    pub fn address(&mut self, address: u8) -> Result<(), E> {
-        // TODO: check if address is bigger than 127 and trhow error
-        // TODO: set address command twice?
-        // TODO: check if update was successfull by reading address, new address might be available only after reboot?
-        //if address > 127 {Err(e)}
-        let result = self.i2c.write(self.address, &[Register::ChirpAddress as u8, address]);
-        // TODO: only update new address when change was success fully?
-        // must before address change
+    // read the firmware version so calls the firmware can't handle are refused with
+    // Error::UnsupportedFirmware and workarounds for older firmware are only used where needed
+    pub fn init(&mut self) -> Result<FirmwareVersion, Error<E>> {
+        let firmware = firmware::check(self.version()?)?;
+        self.firmware = Some(firmware);
+        Ok(firmware)
+    }
+
+    pub fn firmware(&self) -> Option<FirmwareVersion> {
+        self.firmware
+    }
+
+    // polling interval of wait_until_idle()
+    pub fn set_backoff(&mut self, backoff: Backoff) {
+        self.backoff = backoff;
+    }
+
+    // monotonic microseconds, traced register accesses then log how long they took
+    #[cfg(any(feature = "defmt", feature = "log"))]
+    pub fn set_trace_clock(&mut self, clock: fn() -> u64) {
+        self.tracer.set_clock(clock);
+    }
+
+    // change the address of the sensor, the new address is only taken after a reset so this waits
+    // STARTUP_DELAY_MS, waits until the sensor is idle and reads it back at the new address. Without
+    // init() the firmware is probed first, the read back register depends on it. On any failure the
+    // driver keeps talking to the previous address, failures after the address was sent are
+    // reported as Error::AddressUnconfirmed.
+    pub fn address<D: DelayNs>(&mut self, address: u8, delay: &mut D) -> Result<(), Error<E>> {
+        if !(MIN_ADDRESS..=MAX_ADDRESS).contains(&address) {
+            return Err(Error::InvalidAddress(address));
+        }
+        if self.firmware.is_none() {
+            self.init()?;
+        }
+        // the firmware only accepts the new address if the command is sent twice
+        for _ in 0..2 {
+            self.write_register(Register::ChirpAddress, Some(address))?;
+        }
+
+        let previous = self.address;
+        if self.confirm_address(address, delay).is_err() {
+            self.address = previous;
+            return Err(Error::AddressUnconfirmed { previous, address });
+        }
+        Ok(())
+    }
+
+    // reset still goes to the old address, the new address is used after reboot
+    fn confirm_address<D: DelayNs>(&mut self, address: u8, delay: &mut D) -> Result<(), Error<E>> {
         self.reset()?;
+        delay.delay_ms(STARTUP_DELAY_MS);
         self.address = address;
-        result
+        self.wait_until_idle(delay, MEASUREMENT_TIMEOUT_MS)?;
+        self.verify_address()
+    }
+
+    // check the sensor answers at self.address, firmware without the get address register is
+    // checked by reading its version instead
+    fn verify_address(&mut self) -> Result<(), Error<E>> {
+        let (register, expected) = firmware::address_check(self.firmware, self.address);
+        let mut buffer = [0u8; 1];
+        self.read_register(register, &mut buffer)?;
+        if buffer[0] != expected {
+            return Err(Error::UnexpectedValue { register: register as u8, expected, found: buffer[0] });
+        }
+        Ok(())
+    }
+
+    // address the driver currently talks to
+    pub fn get_address(&self) -> u8 {
+        self.address
     }
 
-    pub fn reset(&mut self) -> Result<(), E> {
-        self.i2c.write(self.address, &[Register::ChirpReset as u8])
+    // a running measurement is lost
+    pub fn reset(&mut self) -> Result<(), Error<E>> {
+        self.measuring = false;
+        self.write_register(Register::ChirpReset, None)
+    }
+
+    // reset and wait until the sensor is back up and idle
+    pub fn restart<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<E>> {
+        self.reset()?;
+        delay.delay_ms(STARTUP_DELAY_MS);
+        self.wait_until_idle(delay, MEASUREMENT_TIMEOUT_MS).map(|_| ())
+    }
+
+    // poll the busy register with the configured backoff until the sensor is idle, returns the
+    // milliseconds waited or Error::BusyTimeout if it is still busy after timeout_ms
+    pub fn wait_until_idle<D: DelayNs>(&mut self, delay: &mut D, timeout_ms: u32) -> Result<u32, Error<E>> {
+        let mut waited = 0;
+        let mut interval = self.backoff.initial_ms.max(1);
+        while self.busy()? {
+            if waited >= timeout_ms {
+                return Err(Error::BusyTimeout);
+            }
+            let step = interval.min(timeout_ms - waited);
+            delay.delay_ms(step);
+            waited += step;
+            interval = self.backoff.next(interval);
+        }
+        Ok(waited)
+    }
+
+    // put the sensor into sleep mode, any following I2C transaction wakes it up again
+    pub fn sleep(&mut self) -> Result<(), Error<E>> {
+        firmware::require(self.firmware, Feature::Sleep)?;
+        self.write_register(Register::ChirpSleep, None)
+    }
+
+    // wake the sensor up after sleep(). The first transaction only wakes the chip and is usually not
+    // acknowledged, so its result is ignored. After WAKE_DELAY_MS the version is read to check the
+    // sensor is back. The first capacitance reading after waking still returns the value measured
+    // before sleep, discard it and read again.
+    pub fn wake<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<E>> {
+        let _ = self.version();
+        delay.delay_ms(WAKE_DELAY_MS);
+        self.version().map(|_| ())
     }
 
     // start mussure for light, wait 3 seconds until reading light result
-    pub fn messure(&mut self) -> Result<(), E> {
-        self.i2c.write(self.address, &[Register::ChirpLightMessure as u8])
+    pub fn messure(&mut self) -> Result<(), Error<E>> {
+        self.write_register(Register::ChirpLightMessure, None)
+    }
+
+    // start a light measurement and wait until the sensor has the new result
+    pub fn measure_light<D: DelayNs>(&mut self, delay: &mut D) -> Result<LightReading, Error<E>> {
+        self.messure()?;
+        self.wait_until_idle(delay, MEASUREMENT_TIMEOUT_MS)?;
+        self.light_reading()
+    }
+
+    // start a light measurement, poll read_light() with the returned token for the result
+    pub fn start_measurement(&mut self) -> Result<Measurement, Error<E>> {
+        self.messure()?;
+        self.measuring = true;
+        Ok(Measurement { address: self.address })
+    }
+
+    // raw light count of a started measurement, WouldBlock as long as the sensor is busy so the
+    // previous result is never returned. The result is handed out once, reading it again or with
+    // the token of another sensor fails with Error::NoMeasurement
+    pub fn read_light(&mut self, measurement: &Measurement) -> nb::Result<LightReading, Error<E>> {
+        if !self.measuring || measurement.address != self.address {
+            return Err(nb::Error::Other(Error::NoMeasurement));
+        }
+        if self.busy()? {
+            return Err(nb::Error::WouldBlock);
+        }
+        let light = self.light_reading()?;
+        self.measuring = false;
+        Ok(light)
+    }
+
+    // read all channels in one go: capacitance and temperature first, then a light measurement
+    // which is waited for by polling the busy register
+    pub fn read_all<D: DelayNs>(&mut self, delay: &mut D) -> Result<Reading, Error<E>> {
+        let mut waited = 0;
+        if !firmware::known_to_support(self.firmware, Feature::CapacitanceFix) {
+            self.capacitance()?;
+            delay.delay_ms(CAPACITANCE_SETTLE_MS);
+            waited += CAPACITANCE_SETTLE_MS;
+        }
+        let moisture = self.capacitance()?;
+        let temperature = self.temperature()?;
+
+        self.messure()?;
+        waited += self.wait_until_idle(delay, MEASUREMENT_TIMEOUT_MS)?;
+        let light = self.light_reading()?;
+        Ok(Reading { moisture, temperature, light, timestamp_hint: waited })
     }
 
     // check if busy
-    pub fn busy(&mut self) -> Result<bool, E> {
+    pub fn busy(&mut self) -> Result<bool, Error<E>> {
         let mut buffer = [0u8; 1];
-        self.i2c.write_read(self.address, &[Register::ChirpBusy as u8], &mut buffer)?;
+        self.read_register(Register::ChirpBusy, &mut buffer)?;
         // better way to cast u8 to bool?
         if buffer[0] > 0 {Ok(true)} else {Ok(false)}
     }
 
     // get version, 0x26 means version 2.6
-    pub fn version(&mut self) -> Result<u8, E> {
+    pub fn version(&mut self) -> Result<u8, Error<E>> {
         let mut buffer = [0u8; 1];
-        self.i2c.write_read(self.address, &[Register::ChirpVersion as u8], &mut buffer)?;
+        self.read_register(Register::ChirpVersion, &mut buffer)?;
         Ok(buffer[0] as u8)
     }
 
-    // read light, re-read after 3 seconds other wise previous result will be returned
-    pub fn light(&mut self) -> Result<f32, E> {
-        // create buffer of type u8 with value zero and length of two
+    // result of the last light measurement without checking if it is finished
+    pub fn light_reading(&mut self) -> Result<LightReading, Error<E>> {
         let mut buffer = [0u8; 2];
-        self.i2c.write_read(self.address, &[Register::ChirpLight as u8], &mut buffer)?;
-        Ok((((buffer[0] as u16) << 8 | buffer[1] as u16) as f32) / 10.0f32)
+        self.read_register(Register::ChirpLight, &mut buffer)?;
+        Ok(LightReading::new(u16::from_be_bytes(buffer)))
+    }
+
+    // read light, re-read after 3 seconds other wise previous result will be returned, same as
+    // light_reading()
+    pub fn light(&mut self) -> Result<LightReading, Error<E>> {
+        self.light_reading()
     }
 
-    pub fn temperature(&mut self) -> Result<f32, E> {
+    pub fn temperature(&mut self) -> Result<Temperature, Error<E>> {
+        Ok(Temperature(self.temperature_raw()?))
+    }
+
+    // temperature in tenths of a degree celsius, e.g. -15 means -1.5 °C
+    pub fn temperature_raw(&mut self) -> Result<i16, Error<E>> {
         // create buffer of type u8 with value zero and length of two
         let mut buffer = [0u8; 2];
-        self.i2c.write_read(self.address, &[Register::ChirpTemperature as u8], &mut buffer)?;
-        Ok((((buffer[0] as u16) << 8 | buffer[1] as u16) as f32) /10.0f32)
+        self.read_register(Register::ChirpTemperature, &mut buffer)?;
+        // register is a signed two's complement value
+        Ok(i16::from_be_bytes(buffer))
     }
 
-    pub fn capacitance(&mut self) -> Result<u16, E> {
+    pub fn capacitance(&mut self) -> Result<Moisture, Error<E>> {
         // create buffer of type u8 with value zero and length of two
         let mut buffer = [0u8; 2];
-        self.i2c.write_read(self.address, &[Register::ChirpCapacitance as u8], &mut buffer)?;
-        Ok((buffer[0] as u16) << 8 | buffer[1] as u16)
+        self.read_register(Register::ChirpCapacitance, &mut buffer)?;
+        Ok(Moisture((buffer[0] as u16) << 8 | buffer[1] as u16))
     }
-}
\ No newline at end of file
+
+    // moisture according to the calibration of this sensor
+    pub fn moisture(&mut self, calibration: &Calibration) -> Result<MoisturePercent, Error<E>> {
+        Ok(self.capacitance()?.percent(calibration))
+    }
+
+    fn read_register(&mut self, register: Register, buffer: &mut [u8]) -> Result<(), Error<E>> {
+        let sent = [register as u8];
+        let span = self.tracer.start(self.address, register, &sent);
+        let result = self.i2c.write_read(self.address, &sent, buffer);
+        self.tracer.finish::<I2C>(span, self.address, register, &result, buffer);
+        result.map_err(Error::I2c)
+    }
+
+    // command register, optionally followed by one byte
+    fn write_register(&mut self, register: Register, value: Option<u8>) -> Result<(), Error<E>> {
+        let buffer = [register as u8, value.unwrap_or(0)];
+        let sent = if value.is_some() { &buffer[..] } else { &buffer[..1] };
+        let span = self.tracer.start(self.address, register, sent);
+        let result = self.i2c.write(self.address, sent);
+        self.tracer.finish::<I2C>(span, self.address, register, &result, &[]);
+        result.map_err(Error::I2c)
+    }
+}
@@ -0,0 +1,124 @@
+// Light readings and their conversion to approximate lux.
+//
+// The light register counts how long a photo diode takes to discharge, so higher counts mean
+// darker and the scale is far from linear. A LuxTable maps reference counts, measured next to a
+// lux meter, to lux and interpolates linearly between them.
+
+use core::fmt;
+
+use crate::calibration::MAX_POINTS;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LightReading {
+    raw: u16,
+}
+
+impl LightReading {
+    pub fn new(raw: u16) -> Self {
+        LightReading { raw }
+    }
+
+    // count as read from the register, higher means darker
+    pub fn raw(&self) -> u16 {
+        self.raw
+    }
+
+    // inverted count, goes up when it gets brighter but is still not linear
+    pub fn brightness(&self) -> u16 {
+        u16::MAX - self.raw
+    }
+
+    // same scale as Chirp::light()
+    #[cfg(feature = "float")]
+    pub fn to_f32(&self) -> f32 {
+        self.raw as f32 / 10.0f32
+    }
+
+    pub fn lux(&self, table: &LuxTable) -> u32 {
+        table.lux(self.raw)
+    }
+}
+
+impl fmt::Display for LightReading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.raw)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for LightReading {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=u16}", self.raw)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LuxTableError {
+    // a table needs at least two points
+    TooFewPoints,
+    TooManyPoints,
+    // raw count of the points has to increase strictly
+    NotIncreasing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LuxPoint {
+    pub raw: u16,
+    pub lux: u32,
+}
+
+impl LuxPoint {
+    pub fn new(raw: u16, lux: u32) -> Self {
+        LuxPoint { raw, lux }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LuxTable {
+    points: [LuxPoint; MAX_POINTS],
+    len: u8,
+}
+
+impl LuxTable {
+    // points have to be sorted by strictly increasing raw count
+    pub fn new(points: &[LuxPoint]) -> Result<Self, LuxTableError> {
+        if points.len() < 2 {
+            return Err(LuxTableError::TooFewPoints);
+        }
+        if points.len() > MAX_POINTS {
+            return Err(LuxTableError::TooManyPoints);
+        }
+        if points.windows(2).any(|pair| pair[0].raw >= pair[1].raw) {
+            return Err(LuxTableError::NotIncreasing);
+        }
+        let mut table = LuxTable { points: [LuxPoint::default(); MAX_POINTS], len: points.len() as u8 };
+        table.points[..points.len()].copy_from_slice(points);
+        Ok(table)
+    }
+
+    pub fn points(&self) -> &[LuxPoint] {
+        &self.points[..self.len as usize]
+    }
+
+    // approximate lux for a raw count, clamped to the first and last reference point
+    pub fn lux(&self, raw: u16) -> u32 {
+        let points = self.points();
+        let first = points[0];
+        let last = points[points.len() - 1];
+        if raw <= first.raw {
+            return first.lux;
+        }
+        if raw >= last.raw {
+            return last.lux;
+        }
+        let segment = points
+            .windows(2)
+            .find(|pair| raw <= pair[1].raw)
+            .unwrap_or(&points[points.len() - 2..]);
+        let (low, high) = (segment[0], segment[1]);
+        let offset = (raw - low.raw) as i64;
+        let width = (high.raw - low.raw) as i64;
+        let span = high.lux as i64 - low.lux as i64;
+        (low.lux as i64 + span * offset / width) as u32
+    }
+}
@@ -0,0 +1,128 @@
+// Guided address assignment for new sensors, which all ship at DEFAULT_ADDRESS.
+//
+// Connect one new sensor at a time and call Provisioner::provision_next() for each:
+//
+//     let mut provisioner: Provisioner<16> = Provisioner::new(0x21..=0x40);
+//     loop {
+//         prompt("connect the next sensor");
+//         match provisioner.provision_next(&mut i2c, &mut delay) { ... }
+//     }
+//
+// Before re-addressing, exactly one chirp has to answer at DEFAULT_ADDRESS: the version has to
+// decode to a chirp firmware and the get address register has to read DEFAULT_ADDRESS. Sensors
+// answering together are ANDed by the bus, which usually breaks one of these. Afterwards nothing
+// may answer at DEFAULT_ADDRESS anymore and the sensor at the new address has to report the same
+// firmware, otherwise the sensors are moved back to DEFAULT_ADDRESS. Identical sensors still
+// answer in lockstep and can't be told apart on the bus, so really connect them one by one.
+
+use core::ops::RangeInclusive;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use crate::firmware::{self, FirmwareVersion};
+use crate::{Chirp, Error, Register, DEFAULT_ADDRESS};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProvisionError<E> {
+    Chirp(Error<E>),
+    // nothing answers at DEFAULT_ADDRESS
+    NoSensor,
+    // more than one sensor seems to be connected at DEFAULT_ADDRESS
+    MultipleSensors,
+    // every address of the pool is assigned or taken by another device
+    PoolExhausted,
+    // the mapping has room for N sensors only
+    Full,
+}
+
+impl<E> From<Error<E>> for ProvisionError<E> {
+    fn from(error: Error<E>) -> Self {
+        ProvisionError::Chirp(error)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Assignment {
+    // position in the order the sensors were connected
+    pub index: usize,
+    pub address: u8,
+    pub firmware: FirmwareVersion,
+}
+
+pub struct Provisioner<const N: usize> {
+    pool: RangeInclusive<u8>,
+    assignments: [Assignment; N],
+    len: usize,
+}
+
+impl<const N: usize> Provisioner<N> {
+    pub fn new(pool: RangeInclusive<u8>) -> Self {
+        let empty = Assignment { index: 0, address: 0, firmware: FirmwareVersion::new(0, 0) };
+        Provisioner { pool, assignments: [empty; N], len: 0 }
+    }
+
+    pub fn assignments(&self) -> &[Assignment] {
+        &self.assignments[..self.len]
+    }
+
+    // move the sensor at DEFAULT_ADDRESS to the next free address of the pool and record it
+    pub fn provision_next<I2C, E, D>(&mut self, i2c: &mut I2C, delay: &mut D) -> Result<Assignment, ProvisionError<E>>
+    where
+        I2C: I2c<Error = E>,
+        D: DelayNs,
+    {
+        if self.len == N {
+            return Err(ProvisionError::Full);
+        }
+        if !answers(i2c, DEFAULT_ADDRESS) {
+            return Err(ProvisionError::NoSensor);
+        }
+        let address = self.next_free(i2c).ok_or(ProvisionError::PoolExhausted)?;
+
+        let mut chirp = Chirp::new(&mut *i2c, DEFAULT_ADDRESS);
+        let firmware = chirp.init()?;
+        if !firmware::plausible(firmware) {
+            return Err(ProvisionError::MultipleSensors);
+        }
+        match chirp.verify_address() {
+            Ok(()) => {}
+            Err(Error::UnexpectedValue { .. }) => return Err(ProvisionError::MultipleSensors),
+            Err(error) => return Err(error.into()),
+        }
+
+        let moved = chirp.address(address, delay).and_then(|()| chirp.version());
+        let result = match moved {
+            Ok(version) if FirmwareVersion::from_register(version) == firmware && !answers(i2c, DEFAULT_ADDRESS) => Ok(()),
+            Ok(_) => Err(ProvisionError::MultipleSensors),
+            Err(error) => Err(ProvisionError::Chirp(error)),
+        };
+        if let Err(error) = result {
+            // don't leave sensors on an address that isn't recorded
+            let mut chirp = Chirp::new(&mut *i2c, address);
+            chirp.firmware = Some(firmware);
+            let _ = chirp.address(DEFAULT_ADDRESS, delay);
+            return Err(error);
+        }
+
+        let assignment = Assignment { index: self.len, address, firmware };
+        self.assignments[self.len] = assignment;
+        self.len += 1;
+        Ok(assignment)
+    }
+
+    fn next_free<I2C: I2c>(&self, i2c: &mut I2C) -> Option<u8> {
+        let assigned = self.assignments();
+        self.pool.clone().find(|&address| {
+            address != DEFAULT_ADDRESS
+                && !assigned.iter().any(|assignment| assignment.address == address)
+                && !answers(i2c, address)
+        })
+    }
+}
+
+// any device acknowledging a read of the version register
+fn answers<I2C: I2c>(i2c: &mut I2C, address: u8) -> bool {
+    let mut buffer = [0u8; 1];
+    i2c.write_read(address, &[Register::ChirpVersion as u8], &mut buffer).is_ok()
+}
@@ -0,0 +1,301 @@
+// Sampling each channel at its own interval without blocking.
+//
+// Scheduler wraps a Chirp and a monotonic millisecond clock and decides on every poll() what to do
+// next: read a channel that is due, start a light measurement, check if it is finished or put the
+// sensor to sleep until the next sample. Only one thing happens per poll(), it returns
+// nb::Error::WouldBlock until a sample is ready and next_event_ms() tells when to poll again.
+// next_sample() and samples() do the waiting with a DelayNs.
+//
+//     let mut scheduler = Scheduler::new(chirp, || monotonic_ms());
+//     scheduler.set_interval(Channel::Moisture, Some(60_000));
+//     scheduler.set_interval(Channel::Light, Some(15 * 60_000));
+//     for sample in scheduler.samples(&mut delay) { ... }
+//
+// Nothing else is read while a light measurement runs, moisture and temperature that become due
+// in the meantime are read right after it.
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use crate::firmware::{self, Feature};
+use crate::light::LightReading;
+use crate::units::{Moisture, Temperature};
+use crate::{Chirp, Error, BUSY_POLL_MS, CAPACITANCE_SETTLE_MS, MEASUREMENT_TIMEOUT_MS, WAKE_DELAY_MS};
+
+// monotonic time in milliseconds, any FnMut() -> u64 is one
+pub trait Clock {
+    fn now_ms(&mut self) -> u64;
+}
+
+impl<F: FnMut() -> u64> Clock for F {
+    fn now_ms(&mut self) -> u64 {
+        self()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    Moisture,
+    Temperature,
+    Light,
+}
+
+const CHANNELS: [Channel; 3] = [Channel::Moisture, Channel::Temperature, Channel::Light];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Value {
+    Moisture(Moisture),
+    Temperature(Temperature),
+    Light(LightReading),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    // clock time the value was read
+    pub timestamp_ms: u64,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Schedule {
+    interval_ms: Option<u32>,
+    // None until the first poll()
+    due_ms: Option<u64>,
+}
+
+pub struct Scheduler<I2C, C> {
+    chirp: Chirp<I2C>,
+    clock: C,
+    schedules: [Schedule; 3],
+    sleep: bool,
+    asleep: bool,
+    // sleep is only tried once between samples, a failing sensor isn't sent to sleep on every poll
+    sleep_tried: bool,
+    // wake up transaction sent, sensor answers again at this time
+    awake_at: Option<u64>,
+    // throw away capacitance read at this time, the next read is fresh after CAPACITANCE_SETTLE_MS
+    primed_at: Option<u64>,
+    // start of the running light measurement
+    measuring_since: Option<u64>,
+    // next busy check of the light measurement and the backoff interval that led to it
+    busy_check_at: u64,
+    busy_interval_ms: u32,
+}
+
+impl<I2C, E, C> Scheduler<I2C, C> where I2C: I2c<Error = E>, C: Clock, {
+    // all channels are off until they get an interval
+    pub fn new(chirp: Chirp<I2C>, clock: C) -> Self {
+        let schedule = Schedule { interval_ms: None, due_ms: None };
+        Scheduler {
+            chirp,
+            clock,
+            schedules: [schedule; 3],
+            sleep: false,
+            asleep: false,
+            sleep_tried: false,
+            awake_at: None,
+            primed_at: None,
+            measuring_since: None,
+            busy_check_at: 0,
+            busy_interval_ms: 0,
+        }
+    }
+
+    pub fn destroy(self) -> (Chirp<I2C>, C) {
+        (self.chirp, self.clock)
+    }
+
+    // None stops sampling the channel, a new interval takes effect with the next sample
+    pub fn set_interval(&mut self, channel: Channel, interval_ms: Option<u32>) {
+        let schedule = &mut self.schedules[index(channel)];
+        schedule.interval_ms = interval_ms;
+        if interval_ms.is_none() {
+            schedule.due_ms = None;
+        }
+    }
+
+    // put the sensor to sleep between samples, needs firmware 2.3
+    pub fn set_sleep(&mut self, sleep: bool) -> Result<(), Error<E>> {
+        if sleep {
+            firmware::require(self.chirp.firmware(), Feature::Sleep)?;
+        }
+        self.sleep = sleep;
+        Ok(())
+    }
+
+    // take the next step, a sample once one is ready
+    pub fn poll(&mut self) -> nb::Result<Sample, Error<E>> {
+        let now = self.clock.now_ms();
+        for schedule in self.schedules.iter_mut() {
+            if schedule.interval_ms.is_some() && schedule.due_ms.is_none() {
+                schedule.due_ms = Some(now);
+            }
+        }
+
+        if let Some(awake_at) = self.awake_at {
+            if now < awake_at {
+                return Err(nb::Error::WouldBlock);
+            }
+            self.awake_at = None;
+        }
+        if let Some(since) = self.measuring_since {
+            return self.finish_light(now, since);
+        }
+        let due = CHANNELS.iter().copied().find(|&channel| self.is_due(channel, now));
+        let channel = match due {
+            Some(channel) => channel,
+            None => {
+                if self.sleep && !self.asleep && !self.sleep_tried {
+                    self.sleep_tried = true;
+                    self.chirp.sleep()?;
+                    self.asleep = true;
+                }
+                return Err(nb::Error::WouldBlock);
+            }
+        };
+        if self.asleep {
+            // not acknowledged, only wakes the sensor
+            let _ = self.chirp.version();
+            self.asleep = false;
+            self.awake_at = Some(now + WAKE_DELAY_MS as u64);
+            return Err(nb::Error::WouldBlock);
+        }
+        self.sleep_tried = false;
+
+        let value = match channel {
+            Channel::Moisture => match self.read_moisture(now)? {
+                Some(moisture) => Value::Moisture(moisture),
+                None => return Err(nb::Error::WouldBlock),
+            },
+            Channel::Temperature => Value::Temperature(self.chirp.temperature()?),
+            Channel::Light => {
+                self.chirp.messure()?;
+                self.measuring_since = Some(now);
+                self.busy_interval_ms = self.chirp.backoff.initial_ms.max(1);
+                self.busy_check_at = now + self.busy_interval_ms as u64;
+                return Err(nb::Error::WouldBlock);
+            }
+        };
+        self.reschedule(channel, now);
+        Ok(Sample { timestamp_ms: now, value })
+    }
+
+    // clock time when poll() has something to do next, None if no channel is on
+    pub fn next_event_ms(&mut self) -> Option<u64> {
+        let now = self.clock.now_ms();
+        if let Some(awake_at) = self.awake_at {
+            return Some(awake_at);
+        }
+        if let Some(since) = self.measuring_since {
+            return Some(self.busy_check_at.min(since + MEASUREMENT_TIMEOUT_MS as u64));
+        }
+        if let Some(primed_at) = self.primed_at {
+            return Some(primed_at + CAPACITANCE_SETTLE_MS as u64);
+        }
+        self.schedules
+            .iter()
+            .filter(|schedule| schedule.interval_ms.is_some())
+            .map(|schedule| schedule.due_ms.unwrap_or(now))
+            .min()
+    }
+
+    // poll and wait until the next sample, waits BUSY_POLL_MS at a time if no channel is on
+    pub fn next_sample<D: DelayNs>(&mut self, delay: &mut D) -> Result<Sample, Error<E>> {
+        loop {
+            match self.poll() {
+                Ok(sample) => return Ok(sample),
+                Err(nb::Error::Other(error)) => return Err(error),
+                Err(nb::Error::WouldBlock) => {
+                    let now = self.clock.now_ms();
+                    let next = self.next_event_ms().unwrap_or(now + BUSY_POLL_MS as u64);
+                    let wait = next.saturating_sub(now).min(u32::MAX as u64) as u32;
+                    if wait > 0 {
+                        delay.delay_ms(wait);
+                    }
+                }
+            }
+        }
+    }
+
+    // endless stream of samples
+    pub fn samples<'a, D: DelayNs>(&'a mut self, delay: &'a mut D) -> Samples<'a, I2C, C, D> {
+        Samples { scheduler: self, delay }
+    }
+
+    fn is_due(&self, channel: Channel, now: u64) -> bool {
+        matches!(self.schedules[index(channel)].due_ms, Some(due) if due <= now)
+    }
+
+    fn reschedule(&mut self, channel: Channel, now: u64) {
+        let schedule = &mut self.schedules[index(channel)];
+        if let (Some(interval), Some(mut due)) = (schedule.interval_ms, schedule.due_ms) {
+            // keep to the grid, skipping samples missed while busy
+            while due <= now {
+                due += interval.max(1) as u64;
+            }
+            schedule.due_ms = Some(due);
+        }
+    }
+
+    // old firmware returns the previous result, read once and come back for the fresh value
+    fn read_moisture(&mut self, now: u64) -> Result<Option<Moisture>, Error<E>> {
+        if firmware::known_to_support(self.chirp.firmware(), Feature::CapacitanceFix) {
+            return self.chirp.capacitance().map(Some);
+        }
+        match self.primed_at {
+            None => {
+                self.chirp.capacitance()?;
+                self.primed_at = Some(now);
+                Ok(None)
+            }
+            Some(primed_at) if now < primed_at + CAPACITANCE_SETTLE_MS as u64 => Ok(None),
+            Some(_) => {
+                self.primed_at = None;
+                self.chirp.capacitance().map(Some)
+            }
+        }
+    }
+
+    fn finish_light(&mut self, now: u64, since: u64) -> nb::Result<Sample, Error<E>> {
+        // no bus access before the next busy check, the timeout is always checked
+        if now < self.busy_check_at.min(since + MEASUREMENT_TIMEOUT_MS as u64) {
+            return Err(nb::Error::WouldBlock);
+        }
+        if self.chirp.busy()? {
+            if now - since < MEASUREMENT_TIMEOUT_MS as u64 {
+                // polled with the backoff of the chirp
+                self.busy_interval_ms = self.chirp.backoff.next(self.busy_interval_ms);
+                self.busy_check_at = now + self.busy_interval_ms as u64;
+                return Err(nb::Error::WouldBlock);
+            }
+            self.measuring_since = None;
+            self.reschedule(Channel::Light, now);
+            return Err(nb::Error::Other(Error::BusyTimeout));
+        }
+        self.measuring_since = None;
+        let light = self.chirp.light_reading()?;
+        self.reschedule(Channel::Light, now);
+        Ok(Sample { timestamp_ms: now, value: Value::Light(light) })
+    }
+}
+
+pub struct Samples<'a, I2C, C, D> {
+    scheduler: &'a mut Scheduler<I2C, C>,
+    delay: &'a mut D,
+}
+
+impl<I2C, E, C, D> Iterator for Samples<'_, I2C, C, D> where I2C: I2c<Error = E>, C: Clock, D: DelayNs, {
+    type Item = Result<Sample, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.scheduler.next_sample(self.delay))
+    }
+}
+
+fn index(channel: Channel) -> usize {
+    match channel {
+        Channel::Moisture => 0,
+        Channel::Temperature => 1,
+        Channel::Light => 2,
+    }
+}
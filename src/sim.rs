@@ -0,0 +1,307 @@
+// Software model of a chirp sensor, it implements the I2c trait so the driver and whole
+// applications can run on the host without hardware.
+//
+// The model follows the firmware register map: a new address is only taken after a reset, a light
+// measurement keeps the sensor busy for a number of bus transactions, capacitance reads return the
+// value of the previous read (before firmware 2.7) and any transaction wakes a sleeping sensor but
+// is not acknowledged.
+
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation, SevenBitAddress};
+
+use crate::firmware::{Feature, FirmwareVersion};
+use crate::{Register, DEFAULT_ADDRESS};
+
+// the sensor (or no device at all) did not acknowledge the transaction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nack;
+
+impl embedded_hal::i2c::Error for Nack {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Unknown)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sensor {
+    address: u8,
+    // address written once, a second identical write arms it
+    address_written: Option<u8>,
+    // address taken on the next reset
+    address_armed: Option<u8>,
+    version: u8,
+    moisture: u16,
+    temperature: i16,
+    light: u16,
+    // capacitance returned by the next read
+    capacitance_result: u16,
+    // result of the last finished light measurement
+    light_result: u16,
+    // transactions left until the running light measurement finishes
+    measuring: u8,
+    measurement_transactions: u8,
+    asleep: bool,
+}
+
+impl Sensor {
+    pub fn new(address: u8) -> Self {
+        Sensor {
+            address,
+            address_written: None,
+            address_armed: None,
+            version: 0x26,
+            moisture: 0,
+            temperature: 0,
+            light: 0xffff,
+            capacitance_result: 0,
+            light_result: 0xffff,
+            measuring: 0,
+            measurement_transactions: 3,
+            asleep: false,
+        }
+    }
+
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    pub fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    // raw capacitance, returned from the second read on unless the firmware has the capacitance fix
+    pub fn set_moisture(&mut self, capacitance: u16) {
+        self.moisture = capacitance;
+    }
+
+    // temperature in tenths of a degree celsius
+    pub fn set_temperature(&mut self, temperature: i16) {
+        self.temperature = temperature;
+    }
+
+    // raw light count, higher means darker. Only visible after the next light measurement.
+    pub fn set_light(&mut self, light: u16) {
+        self.light = light;
+    }
+
+    // number of bus transactions to this sensor a light measurement keeps it busy
+    pub fn set_measurement_transactions(&mut self, transactions: u8) {
+        self.measurement_transactions = transactions;
+    }
+
+    pub fn is_busy(&self) -> bool {
+        self.measuring > 0
+    }
+
+    pub fn is_asleep(&self) -> bool {
+        self.asleep
+    }
+
+    // finish a running light measurement right away
+    pub fn complete_measurement(&mut self) {
+        if self.measuring > 0 {
+            self.measuring = 0;
+            self.light_result = self.light;
+        }
+    }
+
+    // power cycle, takes the armed address like a reset does
+    pub fn reset(&mut self) {
+        if let Some(address) = self.address_armed.take() {
+            self.address = address;
+        }
+        self.address_written = None;
+        self.measuring = 0;
+        self.asleep = false;
+        // the first capacitance read after start up returns garbage
+        self.capacitance_result = 0;
+    }
+
+    fn tick(&mut self) {
+        if self.measuring > 0 {
+            self.measuring -= 1;
+            if self.measuring == 0 {
+                self.light_result = self.light;
+            }
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<(), Nack> {
+        self.wake()?;
+        // address only, acknowledged by the hardware without reaching the firmware
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        self.tick();
+        let address_written = self.address_written.take();
+        match bytes {
+            [register, address] if *register == Register::ChirpAddress as u8 => {
+                if address_written == Some(*address) {
+                    self.address_armed = Some(*address);
+                }
+                self.address_written = Some(*address);
+            }
+            [register] if *register == Register::ChirpReset as u8 => self.reset(),
+            [register] if *register == Register::ChirpLightMessure as u8 => {
+                self.measuring = self.measurement_transactions;
+                if self.measuring == 0 {
+                    self.light_result = self.light;
+                }
+            }
+            [register] if *register == Register::ChirpSleep as u8 && self.supports(Feature::Sleep) => self.asleep = true,
+            _ => return Err(Nack),
+        }
+        Ok(())
+    }
+
+    fn read_register(&mut self, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Nack> {
+        self.wake()?;
+        self.tick();
+        self.address_written = None;
+        let register = match bytes {
+            [register] => *register,
+            _ => return Err(Nack),
+        };
+        let value = if register == Register::ChirpCapacitance as u8 {
+            let result = if self.supports(Feature::CapacitanceFix) { self.moisture } else { self.capacitance_result };
+            self.capacitance_result = self.moisture;
+            result.to_be_bytes()
+        } else if register == Register::ChirpGetAddress as u8 && self.supports(Feature::AddressReadback) {
+            [self.address, 0xff]
+        } else if register == Register::ChirpLight as u8 {
+            self.light_result.to_be_bytes()
+        } else if register == Register::ChirpTemperature as u8 {
+            self.temperature.to_be_bytes()
+        } else if register == Register::ChirpVersion as u8 {
+            [self.version, 0xff]
+        } else if register == Register::ChirpBusy as u8 {
+            [self.is_busy() as u8, 0xff]
+        } else {
+            return Err(Nack);
+        };
+        // reading past the value gives an idle bus
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = *value.get(i).unwrap_or(&0xff);
+        }
+        Ok(())
+    }
+
+    fn supports(&self, feature: Feature) -> bool {
+        FirmwareVersion::from_register(self.version).supports(feature)
+    }
+
+    // a sleeping sensor wakes up on the transaction but does not acknowledge it
+    fn wake(&mut self) -> Result<(), Nack> {
+        if self.asleep {
+            self.asleep = false;
+            return Err(Nack);
+        }
+        Ok(())
+    }
+}
+
+impl Default for Sensor {
+    fn default() -> Self {
+        Sensor::new(DEFAULT_ADDRESS)
+    }
+}
+
+impl ErrorType for Sensor {
+    type Error = Nack;
+}
+
+impl I2c for Sensor {
+    fn transaction(&mut self, address: SevenBitAddress, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        if address != self.address {
+            return Err(Nack);
+        }
+        match operations {
+            [Operation::Write(bytes)] => self.write_bytes(bytes),
+            [Operation::Write(bytes), Operation::Read(buffer)] => self.read_register(bytes, buffer),
+            _ => Err(Nack),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for Sensor {
+    async fn transaction(&mut self, address: SevenBitAddress, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        I2c::transaction(self, address, operations)
+    }
+}
+
+// several sensors on one bus. Sensors sharing an address all take writes and, like open drain
+// lines do, a read returns the bitwise and of their answers.
+#[derive(Debug, Clone)]
+pub struct Bus<const N: usize> {
+    sensors: [Sensor; N],
+}
+
+impl<const N: usize> Bus<N> {
+    pub fn new(sensors: [Sensor; N]) -> Self {
+        Bus { sensors }
+    }
+
+    // first sensor currently answering at address
+    pub fn sensor(&self, address: u8) -> Option<&Sensor> {
+        self.sensors.iter().find(|sensor| sensor.address == address)
+    }
+
+    pub fn sensor_mut(&mut self, address: u8) -> Option<&mut Sensor> {
+        self.sensors.iter_mut().find(|sensor| sensor.address == address)
+    }
+
+    pub fn sensors(&self) -> &[Sensor] {
+        &self.sensors
+    }
+
+    pub fn sensors_mut(&mut self) -> &mut [Sensor] {
+        &mut self.sensors
+    }
+}
+
+impl<const N: usize> ErrorType for Bus<N> {
+    type Error = Nack;
+}
+
+impl<const N: usize> I2c for Bus<N> {
+    fn transaction(&mut self, address: SevenBitAddress, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        let mut result = Err(Nack);
+        match operations {
+            [Operation::Write(bytes)] => {
+                for sensor in self.sensors.iter_mut().filter(|sensor| sensor.address == address) {
+                    if sensor.write_bytes(bytes).is_ok() {
+                        result = Ok(());
+                    }
+                }
+            }
+            [Operation::Write(bytes), Operation::Read(buffer)] => {
+                for byte in buffer.iter_mut() {
+                    *byte = 0xff;
+                }
+                let mut answer = [0u8; 2];
+                for sensor in self.sensors.iter_mut().filter(|sensor| sensor.address == address) {
+                    let answer = &mut answer[..buffer.len().min(2)];
+                    if sensor.read_register(bytes, answer).is_ok() {
+                        for (byte, bit) in buffer.iter_mut().zip(answer.iter()) {
+                            *byte &= *bit;
+                        }
+                        result = Ok(());
+                    }
+                }
+            }
+            _ => {}
+        }
+        result
+    }
+}
+
+#[cfg(feature = "async")]
+impl<const N: usize> embedded_hal_async::i2c::I2c for Bus<N> {
+    async fn transaction(&mut self, address: SevenBitAddress, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        I2c::transaction(self, address, operations)
+    }
+}
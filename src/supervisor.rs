@@ -0,0 +1,191 @@
+// Watchdog for sensors on long running installs.
+//
+// After brownouts a chirp sometimes stops answering or keeps returning the same values. Supervisor
+// wraps a Chirp and checks every read_all() for
+//
+// - bus errors, including NACKs and busy timeouts
+// - stuck readings: the same raw values for stuck_cycles reads in a row
+// - implausible values outside of the configured Limits
+//
+// and then restarts the sensor: reset(), wait STARTUP_DELAY_MS until it is idle and check version()
+// still reads the firmware seen before. A sensor that doesn't come back is retried on the next
+// read, poll() never fails so a dead sensor doesn't stop the application loop.
+
+use core::ops::RangeInclusive;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::I2c;
+
+use crate::firmware;
+use crate::{Chirp, Error, Reading, Register};
+
+// reads with identical values before the sensor counts as stuck
+pub const STUCK_CYCLES: u32 = 10;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    // raw capacitance, 0 and 0xffff come from a sensor that just reset or from a floating bus
+    pub moisture: RangeInclusive<u16>,
+    // tenths of a degree celsius, the operating range of the sensor
+    pub temperature: RangeInclusive<i16>,
+}
+
+impl Limits {
+    pub fn contains(&self, reading: &Reading) -> bool {
+        self.moisture.contains(&reading.moisture.raw()) && self.temperature.contains(&reading.temperature.deci_celsius())
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits { moisture: 1..=0xfffe, temperature: -400..=850 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fault<E> {
+    Bus(Error<E>),
+    Stuck,
+    Implausible(Reading),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status<E> {
+    Reading(Reading),
+    // fault detected, the sensor was restarted and answers again
+    Recovered(Fault<E>),
+    // fault detected and the restart failed, tried again on the next poll()
+    Failed(Fault<E>, Error<E>),
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub polls: u32,
+    pub readings: u32,
+    pub bus_errors: u32,
+    pub stuck: u32,
+    pub implausible: u32,
+    pub recoveries: u32,
+    pub failed_recoveries: u32,
+    // failed restarts since the last good reading
+    pub consecutive_failures: u32,
+}
+
+pub struct Supervisor<I2C> {
+    chirp: Chirp<I2C>,
+    limits: Limits,
+    stuck_cycles: Option<u32>,
+    last: Option<Reading>,
+    repeated: u32,
+    // version register of the sensor, from init() or the first good reading
+    version: Option<u8>,
+    stats: Stats,
+}
+
+impl<I2C, E> Supervisor<I2C> where I2C: I2c<Error = E>, {
+    pub fn new(chirp: Chirp<I2C>) -> Self {
+        let version = chirp.firmware().map(|firmware| firmware.to_register());
+        Supervisor {
+            chirp,
+            limits: Limits::default(),
+            stuck_cycles: Some(STUCK_CYCLES),
+            last: None,
+            repeated: 0,
+            version,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn destroy(self) -> Chirp<I2C> {
+        self.chirp
+    }
+
+    pub fn chirp(&mut self) -> &mut Chirp<I2C> {
+        &mut self.chirp
+    }
+
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.limits = limits;
+    }
+
+    // None turns the stuck detection off
+    pub fn set_stuck_cycles(&mut self, stuck_cycles: Option<u32>) {
+        self.stuck_cycles = stuck_cycles;
+        self.repeated = 0;
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.stats
+    }
+
+    // take a reading and restart the sensor if something is wrong with it
+    pub fn poll<D: DelayNs>(&mut self, delay: &mut D) -> Status<E> {
+        self.stats.polls += 1;
+        let fault = match self.chirp.read_all(delay) {
+            Ok(reading) => match self.check(reading) {
+                None => {
+                    if self.version.is_none() {
+                        self.version = self.chirp.version().ok();
+                    }
+                    self.stats.readings += 1;
+                    self.stats.consecutive_failures = 0;
+                    return Status::Reading(reading);
+                }
+                Some(fault) => fault,
+            },
+            Err(error) => {
+                self.stats.bus_errors += 1;
+                Fault::Bus(error)
+            }
+        };
+        match self.recover(delay) {
+            Ok(()) => {
+                self.stats.recoveries += 1;
+                Status::Recovered(fault)
+            }
+            Err(error) => {
+                self.stats.failed_recoveries += 1;
+                self.stats.consecutive_failures += 1;
+                Status::Failed(fault, error)
+            }
+        }
+    }
+
+    fn check(&mut self, reading: Reading) -> Option<Fault<E>> {
+        if !self.limits.contains(&reading) {
+            self.stats.implausible += 1;
+            return Some(Fault::Implausible(reading));
+        }
+        // timestamp_hint differs between reads anyway
+        let same = matches!(self.last, Some(last)
+            if (last.moisture, last.temperature, last.light) == (reading.moisture, reading.temperature, reading.light));
+        self.repeated = if same { self.repeated + 1 } else { 0 };
+        self.last = Some(reading);
+        match self.stuck_cycles {
+            Some(stuck_cycles) if self.repeated + 1 >= stuck_cycles => {
+                self.stats.stuck += 1;
+                Some(Fault::Stuck)
+            }
+            _ => None,
+        }
+    }
+
+    // restart, check the version and throw away the first capacitance reading
+    fn recover<D: DelayNs>(&mut self, delay: &mut D) -> Result<(), Error<E>> {
+        self.last = None;
+        self.repeated = 0;
+        self.chirp.restart(delay)?;
+        let found = self.chirp.version()?;
+        match self.version {
+            Some(expected) if found != expected => {
+                return Err(Error::UnexpectedValue { register: Register::ChirpVersion as u8, expected, found });
+            }
+            Some(_) => {}
+            None => {
+                firmware::check::<E>(found)?;
+            }
+        }
+        self.chirp.capacitance()?;
+        Ok(())
+    }
+}
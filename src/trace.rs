@@ -0,0 +1,117 @@
+// Tracing of register accesses with the defmt and log features.
+//
+// Every access is traced twice, before with the bytes sent and after with the bytes received or the
+// error, so a hung transaction shows up as a start without an end. With a clock set through
+// Chirp::set_trace_clock() the end also logs how long the access took, without one the timing is
+// left to the timestamps of the logger, which log records don't have. Without the features Tracer
+// and Span are empty and the calls are inlined away.
+
+#[cfg(any(feature = "defmt", feature = "log"))]
+use core::fmt;
+
+#[cfg(feature = "defmt")]
+use embedded_hal::i2c::{Error as _, ErrorKind, NoAcknowledgeSource};
+use embedded_hal::i2c::ErrorType;
+
+use crate::Register;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct Tracer {
+    // monotonic microseconds
+    #[cfg(any(feature = "defmt", feature = "log"))]
+    clock: Option<fn() -> u64>,
+}
+
+// a started access
+pub(crate) struct Span {
+    #[cfg(any(feature = "defmt", feature = "log"))]
+    started: Option<u64>,
+}
+
+impl Tracer {
+    #[cfg(any(feature = "defmt", feature = "log"))]
+    pub(crate) fn set_clock(&mut self, clock: fn() -> u64) {
+        self.clock = Some(clock);
+    }
+
+    #[cfg_attr(not(any(feature = "defmt", feature = "log")), allow(unused_variables))]
+    #[inline(always)]
+    pub(crate) fn start(&self, address: u8, register: Register, sent: &[u8]) -> Span {
+        #[cfg(feature = "defmt")]
+        defmt::trace!("chirp 0x{=u8:02x} {=str} sent {=[u8]:02x}", address, register.name(), sent);
+        #[cfg(feature = "log")]
+        log::trace!("chirp 0x{:02x} {} sent {:02x?}", address, register.name(), sent);
+        Span {
+            #[cfg(any(feature = "defmt", feature = "log"))]
+            started: self.clock.map(|clock| clock()),
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "defmt", feature = "log")), allow(unused_variables))]
+    #[inline(always)]
+    pub(crate) fn finish<I2C: ErrorType>(
+        &self,
+        span: Span,
+        address: u8,
+        register: Register,
+        result: &Result<(), I2C::Error>,
+        received: &[u8],
+    ) {
+        #[cfg(any(feature = "defmt", feature = "log"))]
+        let elapsed = match (self.clock, span.started) {
+            (Some(clock), Some(started)) => Elapsed(Some(clock().wrapping_sub(started))),
+            _ => Elapsed(None),
+        };
+        match result {
+            Ok(()) => {
+                #[cfg(feature = "defmt")]
+                defmt::trace!("chirp 0x{=u8:02x} {=str} received {=[u8]:02x}{}", address, register.name(), received, elapsed);
+                #[cfg(feature = "log")]
+                log::trace!("chirp 0x{:02x} {} received {:02x?}{}", address, register.name(), received, elapsed);
+            }
+            Err(error) => {
+                #[cfg(feature = "defmt")]
+                defmt::warn!("chirp 0x{=u8:02x} {=str} failed: {=str}{}", address, register.name(), kind(error.kind()), elapsed);
+                #[cfg(feature = "log")]
+                log::warn!("chirp 0x{:02x} {} failed: {:?}{}", address, register.name(), error, elapsed);
+            }
+        }
+    }
+}
+
+// duration of an access, nothing without a clock
+#[cfg(any(feature = "defmt", feature = "log"))]
+#[derive(Clone, Copy)]
+struct Elapsed(Option<u64>);
+
+#[cfg(any(feature = "defmt", feature = "log"))]
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            Some(us) => write!(f, " in {} us", us),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Elapsed {
+    fn format(&self, f: defmt::Formatter) {
+        if let Some(us) = self.0 {
+            defmt::write!(f, " in {=u64} us", us)
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+fn kind(kind: ErrorKind) -> &'static str {
+    match kind {
+        ErrorKind::Bus => "bus error",
+        ErrorKind::ArbitrationLoss => "arbitration lost",
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address) => "address not acknowledged",
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Data) => "data not acknowledged",
+        ErrorKind::NoAcknowledge(_) => "not acknowledged",
+        ErrorKind::Overrun => "overrun",
+        _ => "other",
+    }
+}
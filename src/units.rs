@@ -0,0 +1,163 @@
+// Reading types that carry their unit.
+//
+// The registers hold fixed-point values, keeping them that way avoids pulling in soft-float code on
+// thumbv6m. Conversion to float happens only when asked for and only with the float feature, the
+// uom feature adds conversions to uom quantities on top of that.
+//
+// - Temperature: tenths of a degree celsius
+// - Moisture: raw capacitance, higher is wetter but depends on the sensor and the soil
+// - MoisturePercent: tenths of a percent, the capacitance mapped through a Calibration
+// - LightReading (in light): raw count, higher is darker
+
+use core::fmt;
+
+use crate::calibration::Calibration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Temperature(pub i16);
+
+impl Temperature {
+    pub fn from_deci_celsius(deci_celsius: i16) -> Self {
+        Temperature(deci_celsius)
+    }
+
+    // tenths of a degree celsius, as in the register
+    pub fn deci_celsius(self) -> i16 {
+        self.0
+    }
+
+    // whole degrees celsius, rounded half away from zero
+    pub fn celsius(self) -> i16 {
+        round_div(self.0 as i32, 10) as i16
+    }
+
+    // tenths of a degree fahrenheit, rounded
+    pub fn deci_fahrenheit(self) -> i32 {
+        round_div(self.0 as i32 * 9, 5) + 320
+    }
+
+    // hundredths of a kelvin, exact
+    pub fn centi_kelvin(self) -> i32 {
+        self.0 as i32 * 10 + 27315
+    }
+
+    // degrees celsius
+    #[cfg(feature = "float")]
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / 10.0f32
+    }
+
+    #[cfg(feature = "float")]
+    pub fn to_fahrenheit_f32(self) -> f32 {
+        self.to_f32() * 1.8f32 + 32.0f32
+    }
+
+    #[cfg(feature = "float")]
+    pub fn to_kelvin_f32(self) -> f32 {
+        self.to_f32() + 273.15f32
+    }
+}
+
+// -1.5 °C
+impl fmt::Display for Temperature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (sign, whole, tenths) = split_tenths(self.0 as i32);
+        write!(f, "{}{}.{} °C", sign, whole, tenths)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Temperature {
+    fn format(&self, f: defmt::Formatter) {
+        let (sign, whole, tenths) = split_tenths(self.0 as i32);
+        defmt::write!(f, "{=str}{=u32}.{=u32} °C", sign, whole, tenths)
+    }
+}
+
+#[cfg(feature = "uom")]
+impl From<Temperature> for uom::si::f32::ThermodynamicTemperature {
+    fn from(temperature: Temperature) -> Self {
+        uom::si::f32::ThermodynamicTemperature::new::<uom::si::thermodynamic_temperature::degree_celsius>(
+            temperature.to_f32(),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Moisture(pub u16);
+
+impl Moisture {
+    pub fn raw(self) -> u16 {
+        self.0
+    }
+
+    pub fn percent(self, calibration: &Calibration) -> MoisturePercent {
+        MoisturePercent(calibration.moisture(self.0))
+    }
+}
+
+impl fmt::Display for Moisture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Moisture {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=u16}", self.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MoisturePercent(pub u16);
+
+impl MoisturePercent {
+    // tenths of a percent, 0 to 1000
+    pub fn deci_percent(self) -> u16 {
+        self.0
+    }
+
+    // whole percent, rounded
+    pub fn percent(self) -> u16 {
+        self.0.saturating_add(5) / 10
+    }
+
+    #[cfg(feature = "float")]
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / 10.0f32
+    }
+}
+
+// 25.0 %
+impl fmt::Display for MoisturePercent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} %", self.0 / 10, self.0 % 10)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for MoisturePercent {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{=u16}.{=u16} %", self.0 / 10, self.0 % 10)
+    }
+}
+
+#[cfg(feature = "uom")]
+impl From<MoisturePercent> for uom::si::f32::Ratio {
+    fn from(moisture: MoisturePercent) -> Self {
+        uom::si::f32::Ratio::new::<uom::si::ratio::percent>(moisture.to_f32())
+    }
+}
+
+fn round_div(value: i32, divisor: i32) -> i32 {
+    let half = if value < 0 { -divisor / 2 } else { divisor / 2 };
+    (value + half) / divisor
+}
+
+// sign, whole and tenths of a fixed-point value, -5 is ("-", 0, 5)
+fn split_tenths(value: i32) -> (&'static str, u32, u32) {
+    let sign = if value < 0 { "-" } else { "" };
+    let value = value.unsigned_abs();
+    (sign, value / 10, value % 10)
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use chirp::alarm::{Alarms, Condition, Event, Threshold};
+use chirp::light::LightReading;
+use chirp::sim::Sensor;
+use chirp::units::{Moisture, Temperature};
+use chirp::{Chirp, Reading, DEFAULT_ADDRESS};
+use common::Delay;
+
+fn reading(moisture: u16, temperature: i16, light: u16) -> Reading {
+    Reading { moisture: Moisture(moisture), temperature: Temperature(temperature), light: LightReading::new(light), timestamp_hint: 0 }
+}
+
+fn events(alarms: &mut Alarms, reading: Reading) -> Vec<Event> {
+    alarms.update(&reading).collect()
+}
+
+#[test]
+fn dry_with_hysteresis() {
+    let mut alarms = Alarms::new();
+    alarms.set_threshold(Condition::Dry, Some(Threshold::new(300, 20)));
+    assert_eq!(events(&mut alarms, reading(350, 200, 1000)), []);
+    assert_eq!(events(&mut alarms, reading(299, 200, 1000)), [Event::BecameDry]);
+    assert!(alarms.is_active(Condition::Dry));
+    // jitter around the threshold doesn't flap
+    assert_eq!(events(&mut alarms, reading(305, 200, 1000)), []);
+    assert_eq!(events(&mut alarms, reading(298, 200, 1000)), []);
+    assert_eq!(events(&mut alarms, reading(319, 200, 1000)), []);
+    assert_eq!(events(&mut alarms, reading(320, 200, 1000)), [Event::Recovered(Condition::Dry)]);
+    assert!(!alarms.is_active(Condition::Dry));
+}
+
+#[test]
+fn several_conditions() {
+    let mut alarms = Alarms::new();
+    alarms.set_threshold(Condition::Wet, Some(Threshold::new(600, 10)));
+    alarms.set_threshold(Condition::Hot, Some(Threshold::new(350, 10)));
+    alarms.set_threshold(Condition::Cold, Some(Threshold::new(20, 10)));
+    alarms.set_threshold(Condition::Dark, Some(Threshold::new(40_000, 1000)));
+    assert_eq!(events(&mut alarms, reading(700, 10, 50_000)), [Event::BecameWet, Event::BecameCold, Event::BecameDark]);
+    assert_eq!(
+        events(&mut alarms, reading(500, 400, 100)),
+        [Event::Recovered(Condition::Wet), Event::BecameHot, Event::Recovered(Condition::Cold), Event::Recovered(Condition::Dark)]
+    );
+}
+
+#[test]
+fn negative_temperature_threshold() {
+    let mut alarms = Alarms::new();
+    alarms.set_threshold(Condition::Cold, Some(Threshold::new(-20, 5)));
+    assert_eq!(events(&mut alarms, reading(0, -21, 0)), [Event::BecameCold]);
+    assert_eq!(events(&mut alarms, reading(0, -16, 0)), []);
+    assert_eq!(events(&mut alarms, reading(0, -15, 0)), [Event::Recovered(Condition::Cold)]);
+}
+
+#[test]
+fn check_sensor() {
+    let mut sensor = Sensor::default();
+    sensor.set_moisture(250);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    let mut alarms = Alarms::new();
+    alarms.set_threshold(Condition::Dry, Some(Threshold::new(300, 20)));
+    let events: Vec<Event> = alarms.check(&mut chirp, &mut delay).unwrap().collect();
+    assert_eq!(events, [Event::BecameDry]);
+    assert_eq!(alarms.check(&mut chirp, &mut delay).unwrap().count(), 0);
+}
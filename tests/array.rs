@@ -0,0 +1,77 @@
+mod common;
+
+use chirp::sim::{Bus, Nack, Sensor};
+use chirp::units::Moisture;
+use chirp::{Backoff, ChirpArray, Error, CAPACITANCE_SETTLE_MS};
+use common::Delay;
+
+fn sensor(address: u8, moisture: u16, light: u16, transactions: u8) -> Sensor {
+    let mut sensor = Sensor::new(address);
+    sensor.set_moisture(moisture);
+    sensor.set_light(light);
+    sensor.set_measurement_transactions(transactions);
+    sensor
+}
+
+#[test]
+fn read_all_sensors() {
+    let bus = Bus::new([sensor(0x21, 300, 1000, 2), sensor(0x22, 400, 2000, 6), sensor(0x23, 500, 3000, 4)]);
+    let mut array = ChirpArray::new(bus, [0x21, 0x22, 0x23]);
+    let mut delay = Delay::default();
+    let readings = array.read_all(&mut delay);
+    let values: Vec<(u16, u16)> = readings.iter().map(|r| r.map(|r| (r.moisture.raw(), r.light.raw())).unwrap()).collect();
+    assert_eq!(values, [(300, 1000), (400, 2000), (500, 3000)]);
+}
+
+#[test]
+fn measurements_run_in_parallel() {
+    let bus = Bus::new([sensor(0x21, 0, 0, 3), sensor(0x22, 0, 0, 3)]);
+    let mut array = ChirpArray::new(bus, [0x21, 0x22]);
+    assert!(array.start_measurements().iter().all(Result::is_ok));
+    let bus = array.destroy();
+    assert!(bus.sensors().iter().all(Sensor::is_busy));
+}
+
+#[test]
+fn failing_sensor_does_not_stop_others() {
+    let bus = Bus::new([sensor(0x21, 300, 1000, 2), sensor(0x23, 500, 3000, 2)]);
+    let mut array = ChirpArray::new(bus, [0x21, 0x22, 0x23]);
+    let mut delay = Delay::default();
+    let readings = array.read_all(&mut delay);
+    assert_eq!(readings[0].map(|r| r.moisture), Ok(Moisture(300)));
+    assert_eq!(readings[1], Err(Error::I2c(Nack)));
+    assert_eq!(readings[2].map(|r| r.moisture), Ok(Moisture(500)));
+}
+
+#[test]
+fn polls_with_backoff() {
+    let bus = Bus::new([sensor(0x21, 300, 1000, 4), sensor(0x22, 400, 2000, 4)]);
+    let mut array = ChirpArray::new(bus, [0x21, 0x22]);
+    array.set_backoff(Backoff::fixed(1));
+    let mut delay = Delay::default();
+    let readings = array.read_all(&mut delay);
+    assert!(readings.iter().all(Result::is_ok));
+    // the default backoff would wait BUSY_POLL_MS at least once
+    assert!(delay.total_ms < CAPACITANCE_SETTLE_MS + 10);
+}
+
+#[test]
+fn stuck_sensor_times_out() {
+    let bus = Bus::new([sensor(0x21, 300, 1000, 2), sensor(0x22, 400, 2000, u8::MAX)]);
+    let mut array = ChirpArray::new(bus, [0x21, 0x22]);
+    let mut delay = Delay::default();
+    let readings = array.read_all(&mut delay);
+    assert!(readings[0].is_ok());
+    assert_eq!(readings[1], Err(Error::BusyTimeout));
+}
+
+#[test]
+fn borrowed_bus() {
+    let mut bus = Bus::new([sensor(0x21, 0, 0, 1)]);
+    {
+        let mut array = ChirpArray::new(&mut bus, [0x21]);
+        assert_eq!(array.chirp(0).version(), Ok(0x26));
+        assert_eq!(array.addresses(), &[0x21]);
+    }
+    assert!(bus.sensor(0x21).is_some());
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "async")]
+
+mod common;
+
+use chirp::asynch::AsyncChirp;
+use chirp::sim::Sensor;
+use chirp::units::{Moisture, Temperature};
+use chirp::{Error, BUSY_POLL_MS, DEFAULT_ADDRESS, MEASUREMENT_TIMEOUT_MS, STARTUP_DELAY_MS};
+use common::{Delay, Mock, MockError, Transaction};
+use embassy_futures::block_on;
+
+#[test]
+fn readings() {
+    let mock = Mock::new(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x05], &[0xff, 0xf1]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x00], &[0x01, 0x5e]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x04], &[0x12, 0x34]),
+    ]);
+    let mut chirp = AsyncChirp::new(mock, DEFAULT_ADDRESS);
+    block_on(async {
+        assert_eq!(chirp.version().await, Ok(0x26));
+        assert_eq!(chirp.temperature_raw().await, Ok(-15));
+        assert_eq!(chirp.capacitance().await, Ok(Moisture(350)));
+        assert_eq!(chirp.light_reading().await.map(|light| light.raw()), Ok(0x1234));
+    });
+    chirp.destroy().done();
+}
+
+#[test]
+fn measure_light_awaits_idle() {
+    let mock = Mock::new(&[
+        Transaction::write(DEFAULT_ADDRESS, &[0x03]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x04], &[0x00, 0x64]),
+    ]);
+    let mut chirp = AsyncChirp::new(mock, DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    let light = block_on(chirp.measure_light(&mut delay));
+    assert_eq!(light.map(|light| light.raw()), Ok(100));
+    assert_eq!(delay.total_ms, 2 * BUSY_POLL_MS);
+    chirp.destroy().done();
+}
+
+#[test]
+fn measure_light_times_out() {
+    let mut sensor = Sensor::default();
+    sensor.set_measurement_transactions(u8::MAX);
+    let mut chirp = AsyncChirp::new(sensor, DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    assert_eq!(block_on(chirp.measure_light(&mut delay)), Err(Error::BusyTimeout));
+    assert_eq!(delay.total_ms, MEASUREMENT_TIMEOUT_MS);
+}
+
+#[test]
+fn address_change_on_simulated_sensor() {
+    let mut chirp = AsyncChirp::new(Sensor::default(), DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    block_on(chirp.address(0x22, &mut delay)).unwrap();
+    assert_eq!(chirp.get_address(), 0x22);
+    assert_eq!(delay.total_ms, STARTUP_DELAY_MS);
+    assert_eq!(chirp.destroy().address(), 0x22);
+}
+
+#[test]
+fn bus_error() {
+    let mock = Mock::new(&[Transaction::write(DEFAULT_ADDRESS, &[0x06]).with_error()]);
+    let mut chirp = AsyncChirp::new(mock, DEFAULT_ADDRESS);
+    assert_eq!(block_on(chirp.reset()), Err(Error::I2c(MockError)));
+    chirp.destroy().done();
+}
+
+#[test]
+fn read_all() {
+    let mut sensor = Sensor::default();
+    sensor.set_moisture(480);
+    sensor.set_temperature(-20);
+    sensor.set_light(900);
+    let mut chirp = AsyncChirp::new(sensor, DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    let reading = block_on(chirp.read_all(&mut delay)).unwrap();
+    assert_eq!((reading.moisture, reading.temperature, reading.light.raw()), (Moisture(480), Temperature(-20), 900));
+    assert_eq!(reading.timestamp_hint, delay.total_ms);
+}
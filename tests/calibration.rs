@@ -0,0 +1,92 @@
+mod common;
+
+use chirp::calibration::{Calibration, CalibrationError, Point, MAX_POINTS, SERIALIZED_LEN};
+use chirp::units::{Moisture, MoisturePercent};
+use chirp::{Chirp, DEFAULT_ADDRESS};
+use common::{Mock, Transaction};
+
+#[test]
+fn two_point() {
+    let calibration = Calibration::new(250, 650).unwrap();
+    assert_eq!(calibration.moisture(100), 0);
+    assert_eq!(calibration.moisture(250), 0);
+    assert_eq!(calibration.moisture(450), 500);
+    assert_eq!(calibration.moisture(650), 1000);
+    assert_eq!(calibration.moisture(u16::MAX), 1000);
+    #[cfg(feature = "float")]
+    assert_eq!(calibration.moisture_percent(350), 25.0);
+}
+
+#[test]
+fn piecewise_linear() {
+    let calibration = Calibration::from_points(&[
+        Point::new(250, 0),
+        Point::new(300, 100),
+        Point::new(500, 300),
+        Point::new(600, 450),
+    ])
+    .unwrap();
+    assert_eq!(calibration.moisture(275), 50);
+    assert_eq!(calibration.moisture(300), 100);
+    assert_eq!(calibration.moisture(400), 200);
+    assert_eq!(calibration.moisture(550), 375);
+    assert_eq!(calibration.moisture(700), 450);
+}
+
+#[test]
+fn invalid_points() {
+    assert_eq!(Calibration::from_points(&[Point::new(250, 0)]), Err(CalibrationError::TooFewPoints));
+    assert_eq!(Calibration::new(650, 250), Err(CalibrationError::NotIncreasing));
+    assert_eq!(Calibration::new(250, 250), Err(CalibrationError::NotIncreasing));
+    let points: Vec<Point> = (0..=MAX_POINTS as u16).map(|i| Point::new(i * 10, i)).collect();
+    assert_eq!(Calibration::from_points(&points), Err(CalibrationError::TooManyPoints));
+    assert_eq!(
+        Calibration::from_points(&[Point::new(250, 0), Point::new(650, 1001)]),
+        Err(CalibrationError::MoistureOutOfRange)
+    );
+}
+
+#[test]
+fn serialization_round_trip() {
+    let calibration = Calibration::from_points(&[Point::new(250, 0), Point::new(400, 220), Point::new(640, 480)]).unwrap();
+    let bytes = calibration.to_bytes();
+    assert_eq!(bytes.len(), SERIALIZED_LEN);
+    assert_eq!(Calibration::from_bytes(&bytes), Ok(calibration));
+}
+
+#[test]
+fn corrupted_serialization() {
+    let mut bytes = Calibration::new(250, 650).unwrap().to_bytes();
+    bytes[3] ^= 0x01;
+    assert_eq!(Calibration::from_bytes(&bytes), Err(CalibrationError::InvalidData));
+    assert_eq!(Calibration::from_bytes(&[0x01, 0x02]), Err(CalibrationError::InvalidData));
+    assert_eq!(Calibration::from_bytes(&[0u8; SERIALIZED_LEN]), Err(CalibrationError::InvalidData));
+
+    // one point with a matching checksum, the sum drops by one so the checksum grows by one
+    let mut bytes = Calibration::new(250, 650).unwrap().to_bytes();
+    bytes[1] = 1;
+    bytes[SERIALIZED_LEN - 1] = bytes[SERIALIZED_LEN - 1].wrapping_add(1);
+    assert_eq!(Calibration::from_bytes(&bytes), Err(CalibrationError::InvalidData));
+}
+
+#[test]
+fn moisture_from_sensor() {
+    let calibration = Calibration::new(250, 650).unwrap();
+    let mock = Mock::new(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x00], &[0x01, 0xc2])]);
+    let mut chirp = Chirp::new(mock, DEFAULT_ADDRESS);
+    assert_eq!(chirp.moisture(&calibration), Ok(MoisturePercent(500)));
+    chirp.destroy().done();
+}
+
+#[test]
+fn moisture_units() {
+    let calibration = Calibration::new(250, 650).unwrap();
+    let percent = Moisture(450).percent(&calibration);
+    assert_eq!(percent, MoisturePercent(500));
+    assert_eq!(percent.percent(), 50);
+    assert_eq!(MoisturePercent(255).percent(), 26);
+    assert_eq!(MoisturePercent(u16::MAX).percent(), 6553);
+    assert_eq!(percent.to_string(), "50.0 %");
+    assert_eq!(MoisturePercent(1000).to_string(), "100.0 %");
+    assert_eq!(Moisture(450).to_string(), "450");
+}
@@ -0,0 +1,278 @@
+mod common;
+
+use chirp::light::LightReading;
+use chirp::units::{Moisture, Temperature};
+use chirp::{Backoff, Chirp, Error, Reading, BUSY_POLL_MS, CAPACITANCE_SETTLE_MS, DEFAULT_ADDRESS, STARTUP_DELAY_MS, WAKE_DELAY_MS};
+use common::{Delay, Mock, MockError, Transaction};
+
+fn chirp(expected: &[Transaction]) -> Chirp<Mock> {
+    Chirp::new(Mock::new(expected), DEFAULT_ADDRESS)
+}
+
+#[test]
+fn reset() {
+    let mut chirp = chirp(&[Transaction::write(DEFAULT_ADDRESS, &[0x06])]);
+    chirp.reset().unwrap();
+    chirp.destroy().done();
+}
+
+#[test]
+fn messure() {
+    let mut chirp = chirp(&[Transaction::write(DEFAULT_ADDRESS, &[0x03])]);
+    chirp.messure().unwrap();
+    chirp.destroy().done();
+}
+
+#[test]
+fn light_measurement() {
+    let mut chirp = chirp(&[
+        Transaction::write(DEFAULT_ADDRESS, &[0x03]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x04], &[0x12, 0x34]),
+    ]);
+    let measurement = chirp.start_measurement().unwrap();
+    assert_eq!(chirp.read_light(&measurement), Err(nb::Error::WouldBlock));
+    assert_eq!(chirp.read_light(&measurement).map(|light| light.raw()), Ok(0x1234));
+    // the result is only handed out once
+    assert_eq!(chirp.read_light(&measurement), Err(nb::Error::Other(Error::NoMeasurement)));
+    chirp.destroy().done();
+}
+
+#[test]
+fn measurement_of_another_sensor() {
+    let mut first = chirp(&[Transaction::write(DEFAULT_ADDRESS, &[0x03])]);
+    let mut second = Chirp::new(Mock::new(&[Transaction::write(0x21, &[0x03])]), 0x21);
+    let measurement = first.start_measurement().unwrap();
+    assert_eq!(second.read_light(&measurement), Err(nb::Error::Other(Error::NoMeasurement)));
+    let _ = second.start_measurement().unwrap();
+    assert_eq!(second.read_light(&measurement), Err(nb::Error::Other(Error::NoMeasurement)));
+    first.destroy().done();
+    second.destroy().done();
+}
+
+#[test]
+fn read_all() {
+    let mut chirp = chirp(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x00], &[0x00, 0x00]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x00], &[0x01, 0x5e]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x05], &[0x00, 0xeb]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x03]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x04], &[0x12, 0x34]),
+    ]);
+    let mut delay = Delay::default();
+    let reading = chirp.read_all(&mut delay).unwrap();
+    let waited = CAPACITANCE_SETTLE_MS + BUSY_POLL_MS;
+    assert_eq!(
+        reading,
+        Reading { moisture: Moisture(350), temperature: Temperature(235), light: LightReading::new(0x1234), timestamp_hint: waited }
+    );
+    assert_eq!(delay.total_ms, waited);
+    chirp.destroy().done();
+}
+
+#[test]
+fn busy() {
+    let mut chirp = chirp(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00]),
+    ]);
+    assert_eq!(chirp.busy(), Ok(true));
+    assert_eq!(chirp.busy(), Ok(false));
+    chirp.destroy().done();
+}
+
+#[test]
+fn wait_until_idle_backs_off() {
+    let mut chirp = chirp(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00]),
+    ]);
+    chirp.set_backoff(Backoff::exponential(10, 25));
+    let mut delay = Delay::default();
+    assert_eq!(chirp.wait_until_idle(&mut delay, 1000), Ok(55));
+    assert_eq!(delay.total_ms, 55);
+    chirp.destroy().done();
+}
+
+#[test]
+fn wait_until_idle_timeout() {
+    let busy = Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]);
+    let mut chirp = chirp(&vec![busy; 5]);
+    chirp.set_backoff(Backoff::exponential(10, 1000));
+    let mut delay = Delay::default();
+    assert_eq!(chirp.wait_until_idle(&mut delay, 100), Err(Error::BusyTimeout));
+    assert_eq!(delay.total_ms, 100);
+    chirp.destroy().done();
+}
+
+#[test]
+fn wait_until_idle_already_idle() {
+    let mut chirp = chirp(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00])]);
+    let mut delay = Delay::default();
+    assert_eq!(chirp.wait_until_idle(&mut delay, 0), Ok(0));
+    assert_eq!(delay.total_ms, 0);
+    chirp.destroy().done();
+}
+
+#[test]
+fn restart() {
+    let mut chirp = chirp(&[
+        Transaction::write(DEFAULT_ADDRESS, &[0x06]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00]),
+    ]);
+    let mut delay = Delay::default();
+    chirp.restart(&mut delay).unwrap();
+    assert_eq!(delay.total_ms, STARTUP_DELAY_MS + BUSY_POLL_MS);
+    chirp.destroy().done();
+}
+
+#[test]
+fn measure_light() {
+    let mut chirp = chirp(&[
+        Transaction::write(DEFAULT_ADDRESS, &[0x03]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x01]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x04], &[0x12, 0x34]),
+    ]);
+    let mut delay = Delay::default();
+    assert_eq!(chirp.measure_light(&mut delay), Ok(LightReading::new(0x1234)));
+    assert_eq!(delay.total_ms, BUSY_POLL_MS);
+    chirp.destroy().done();
+}
+
+#[test]
+fn version() {
+    let mut chirp = chirp(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26])]);
+    assert_eq!(chirp.version(), Ok(0x26));
+    chirp.destroy().done();
+}
+
+#[test]
+fn light() {
+    let mut chirp = chirp(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x04], &[0x12, 0x34])]);
+    assert_eq!(chirp.light(), Ok(LightReading::new(0x1234)));
+    chirp.destroy().done();
+}
+
+#[test]
+fn temperature() {
+    let mut chirp = chirp(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x05], &[0x00, 0xeb])]);
+    assert_eq!(chirp.temperature(), Ok(Temperature(235)));
+    chirp.destroy().done();
+}
+
+#[test]
+fn capacitance() {
+    let mut chirp = chirp(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x00], &[0x01, 0x5e])]);
+    assert_eq!(chirp.capacitance(), Ok(Moisture(350)));
+    chirp.destroy().done();
+}
+
+#[test]
+fn sleep_and_wake() {
+    let mut chirp = chirp(&[
+        Transaction::write(DEFAULT_ADDRESS, &[0x08]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x00]).with_error(),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+    ]);
+    let mut delay = Delay::default();
+    chirp.sleep().unwrap();
+    chirp.wake(&mut delay).unwrap();
+    assert_eq!(delay.total_ms, WAKE_DELAY_MS);
+    chirp.destroy().done();
+}
+
+#[test]
+fn bus_error() {
+    let mut chirp = chirp(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x00], &[0, 0]).with_error()]);
+    assert_eq!(chirp.capacitance(), Err(Error::I2c(MockError)));
+    chirp.destroy().done();
+}
+
+#[test]
+fn address() {
+    let mut chirp = chirp(&[
+        // firmware probed first, it decides how the new address is read back
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x01, 0x21]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x01, 0x21]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x06]),
+        Transaction::write_read(0x21, &[0x09], &[0x01]),
+        Transaction::write_read(0x21, &[0x09], &[0x00]),
+        Transaction::write_read(0x21, &[0x02], &[0x21]),
+    ]);
+    let mut delay = Delay::default();
+    chirp.address(0x21, &mut delay).unwrap();
+    assert_eq!(chirp.get_address(), 0x21);
+    assert_eq!(delay.total_ms, STARTUP_DELAY_MS + BUSY_POLL_MS);
+    chirp.destroy().done();
+}
+
+#[test]
+fn address_out_of_range() {
+    let mut chirp = chirp(&[]);
+    let mut delay = Delay::default();
+    assert_eq!(chirp.address(0x80, &mut delay), Err(Error::InvalidAddress(0x80)));
+    assert_eq!(chirp.address(0x00, &mut delay), Err(Error::InvalidAddress(0x00)));
+    // reserved addresses, discover() would not find the sensor there
+    assert_eq!(chirp.address(0x07, &mut delay), Err(Error::InvalidAddress(0x07)));
+    assert_eq!(chirp.address(0x78, &mut delay), Err(Error::InvalidAddress(0x78)));
+    assert_eq!(chirp.get_address(), DEFAULT_ADDRESS);
+    chirp.destroy().done();
+}
+
+#[test]
+fn address_rolled_back_on_wrong_readback() {
+    let mut chirp = chirp(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x01, 0x21]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x01, 0x21]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x06]),
+        Transaction::write_read(0x21, &[0x09], &[0x00]),
+        Transaction::write_read(0x21, &[0x02], &[0x20]),
+    ]);
+    let mut delay = Delay::default();
+    assert_eq!(
+        chirp.address(0x21, &mut delay),
+        Err(Error::AddressUnconfirmed { previous: DEFAULT_ADDRESS, address: 0x21 })
+    );
+    assert_eq!(chirp.get_address(), DEFAULT_ADDRESS);
+    chirp.destroy().done();
+}
+
+#[test]
+fn address_rolled_back_on_missing_sensor() {
+    let mut chirp = chirp(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x01, 0x21]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x01, 0x21]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x06]),
+        Transaction::write_read(0x21, &[0x09], &[0x00]).with_error(),
+    ]);
+    let mut delay = Delay::default();
+    assert_eq!(
+        chirp.address(0x21, &mut delay),
+        Err(Error::AddressUnconfirmed { previous: DEFAULT_ADDRESS, address: 0x21 })
+    );
+    assert_eq!(chirp.get_address(), DEFAULT_ADDRESS);
+    chirp.destroy().done();
+}
+
+#[test]
+fn address_write_failure() {
+    let mut chirp = chirp(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x01, 0x21]).with_error(),
+    ]);
+    let mut delay = Delay::default();
+    assert_eq!(chirp.address(0x21, &mut delay), Err(Error::I2c(MockError)));
+    assert_eq!(chirp.get_address(), DEFAULT_ADDRESS);
+    assert_eq!(delay.total_ms, 0);
+    chirp.destroy().done();
+}
@@ -0,0 +1,94 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use chirp::cli::{self, Command, Options, WATCH_INTERVAL_MS};
+use chirp::sim::{Bus, Sensor};
+use common::Delay;
+
+fn options(args: &[&str]) -> Options {
+    cli::parse(args).unwrap()
+}
+
+fn run<I2C: embedded_hal::i2c::I2c>(args: &[&str], i2c: &mut I2C) -> String
+where
+    I2C::Error: std::fmt::Debug,
+{
+    let mut out = Vec::new();
+    cli::run(&options(args), i2c, &mut Delay::default(), &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn parse_options() {
+    assert_eq!(options(&["read"]), Options { device: "/dev/i2c-1".into(), address: 0x20, command: Command::Read });
+    assert_eq!(
+        options(&["--bus", "0", "--address", "0x21", "set-address", "34"]),
+        Options { device: "/dev/i2c-0".into(), address: 0x21, command: Command::SetAddress(0x22) }
+    );
+    assert_eq!(options(&["--device", "/tmp/i2c", "version"]).device, "/tmp/i2c");
+    assert_eq!(
+        options(&["watch", "--count", "3"]).command,
+        Command::Watch { interval_ms: WATCH_INTERVAL_MS, count: Some(3) }
+    );
+}
+
+#[test]
+fn parse_errors() {
+    assert!(cli::parse::<&str>(&[]).is_err());
+    assert!(cli::parse(&["frobnicate"]).is_err());
+    assert!(cli::parse(&["--address", "0xzz", "read"]).is_err());
+    assert!(cli::parse(&["set-address"]).is_err());
+    assert!(cli::parse(&["read", "extra"]).is_err());
+    assert!(cli::parse(&["watch", "--interval"]).is_err());
+}
+
+#[test]
+fn read() {
+    let mut sensor = Sensor::default();
+    sensor.set_moisture(350);
+    sensor.set_temperature(-15);
+    sensor.set_light(1200);
+    assert_eq!(run(&["read"], &mut sensor), "moisture: 350\ntemperature: -1.5 °C\nlight: 1200\n");
+}
+
+#[test]
+fn version() {
+    assert_eq!(run(&["version"], &mut Sensor::default()), "2.6\n");
+}
+
+#[test]
+fn reset() {
+    let mut sensor = Sensor::new(0x21);
+    assert_eq!(run(&["--address", "0x21", "reset"], &mut sensor), "reset 0x21\n");
+}
+
+#[test]
+fn scan() {
+    let mut bus = Bus::new([Sensor::new(0x21), Sensor::new(0x30)]);
+    assert_eq!(run(&["scan"], &mut bus), "0x21 firmware 2.6\n0x30 firmware 2.6\n");
+}
+
+#[test]
+fn set_address() {
+    let mut sensor = Sensor::default();
+    assert_eq!(run(&["set-address", "0x21"], &mut sensor), "moved 0x20 to 0x21\n");
+    assert_eq!(sensor.address(), 0x21);
+}
+
+#[test]
+fn watch() {
+    let mut sensor = Sensor::default();
+    sensor.set_moisture(350);
+    let output = run(&["watch", "--count", "2", "--interval", "10"], &mut sensor);
+    assert_eq!(output.lines().count(), 2);
+    assert!(output.lines().all(|line| line.starts_with("moisture: 350 temperature:")));
+}
+
+#[test]
+fn missing_sensor() {
+    let mut sensor = Sensor::new(0x21);
+    let mut out = Vec::new();
+    let result = cli::run(&options(&["read"]), &mut sensor, &mut Delay::default(), &mut out);
+    assert!(matches!(result, Err(cli::CliError::Chirp(_))));
+}
@@ -0,0 +1,121 @@
+// scripted I2C bus, every transaction the driver does has to match the next expected one
+#![allow(dead_code)]
+
+use std::collections::VecDeque;
+
+use embedded_hal::delay::DelayNs;
+use embedded_hal::i2c::{ErrorKind, ErrorType, I2c, NoAcknowledgeSource, Operation, SevenBitAddress};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transaction {
+    address: u8,
+    bytes: Vec<u8>,
+    // None for a plain write
+    response: Option<Vec<u8>>,
+    error: bool,
+}
+
+impl Transaction {
+    pub fn write(address: u8, bytes: &[u8]) -> Self {
+        Transaction { address, bytes: bytes.to_vec(), response: None, error: false }
+    }
+
+    pub fn write_read(address: u8, bytes: &[u8], response: &[u8]) -> Self {
+        Transaction { address, bytes: bytes.to_vec(), response: Some(response.to_vec()), error: false }
+    }
+
+    // transaction is expected but fails on the bus, e.g. because nothing acknowledges the address
+    pub fn with_error(mut self) -> Self {
+        self.error = true;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockError;
+
+impl embedded_hal::i2c::Error for MockError {
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::NoAcknowledge(NoAcknowledgeSource::Address)
+    }
+}
+
+pub struct Mock {
+    expected: VecDeque<Transaction>,
+}
+
+impl Mock {
+    pub fn new(expected: &[Transaction]) -> Self {
+        Mock { expected: expected.iter().cloned().collect() }
+    }
+
+    // panics if the driver did not do all expected transactions
+    pub fn done(&self) {
+        assert!(self.expected.is_empty(), "transactions left: {:?}", self.expected);
+    }
+
+    fn next(&mut self, address: u8, bytes: &[u8], read: Option<usize>) -> Result<Vec<u8>, MockError> {
+        let expected = self.expected.pop_front().unwrap_or_else(|| {
+            panic!("unexpected transaction to {:#04x} {:?}", address, bytes)
+        });
+        assert_eq!(expected.address, address, "address of {:?}", expected);
+        assert_eq!(expected.bytes, bytes, "bytes of {:?}", expected);
+        assert_eq!(expected.response.as_ref().map(Vec::len), read, "kind of {:?}", expected);
+        if expected.error {
+            return Err(MockError);
+        }
+        Ok(expected.response.unwrap_or_default())
+    }
+}
+
+impl ErrorType for Mock {
+    type Error = MockError;
+}
+
+impl I2c for Mock {
+    fn transaction(&mut self, address: SevenBitAddress, operations: &mut [Operation<'_>]) -> Result<(), MockError> {
+        match operations {
+            [Operation::Write(bytes)] => self.next(address, bytes, None).map(|_| ()),
+            [Operation::Write(bytes), Operation::Read(buffer)] => {
+                let response = self.next(address, bytes, Some(buffer.len()))?;
+                buffer.copy_from_slice(&response);
+                Ok(())
+            }
+            _ => panic!("unsupported transaction to {:#04x}", address),
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::i2c::I2c for Mock {
+    async fn transaction(&mut self, address: SevenBitAddress, operations: &mut [Operation<'_>]) -> Result<(), MockError> {
+        I2c::transaction(self, address, operations)
+    }
+}
+
+// records how long the driver waited instead of sleeping
+#[derive(Debug, Default)]
+pub struct Delay {
+    pub total_ms: u32,
+}
+
+impl DelayNs for Delay {
+    fn delay_ns(&mut self, ns: u32) {
+        self.total_ms += ns / 1_000_000;
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.total_ms += ms;
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_hal_async::delay::DelayNs for Delay {
+    async fn delay_ns(&mut self, ns: u32) {
+        DelayNs::delay_ns(self, ns)
+    }
+
+    async fn delay_ms(&mut self, ms: u32) {
+        DelayNs::delay_ms(self, ms)
+    }
+}
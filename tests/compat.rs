@@ -0,0 +1,63 @@
+#![cfg(feature = "eh02")]
+
+use chirp::compat::{CompatError, DelayCompat, I2cCompat};
+use chirp::sim::{Nack, Sensor};
+use chirp::{Chirp, Error, DEFAULT_ADDRESS};
+use embedded_hal::i2c::I2c;
+use embedded_hal_02::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal_02::blocking::i2c::{Write, WriteRead};
+
+// embedded-hal 0.2 bus in front of the simulated sensor
+struct LegacyBus(Sensor);
+
+impl Write for LegacyBus {
+    type Error = Nack;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Nack> {
+        self.0.write(address, bytes)
+    }
+}
+
+impl WriteRead for LegacyBus {
+    type Error = Nack;
+
+    fn write_read(&mut self, address: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Nack> {
+        self.0.write_read(address, bytes, buffer)
+    }
+}
+
+#[derive(Default)]
+struct LegacyDelay {
+    total_us: u32,
+}
+
+impl DelayUs<u32> for LegacyDelay {
+    fn delay_us(&mut self, us: u32) {
+        self.total_us += us;
+    }
+}
+
+impl DelayMs<u32> for LegacyDelay {
+    fn delay_ms(&mut self, ms: u32) {
+        self.total_us += ms * 1000;
+    }
+}
+
+#[test]
+fn legacy_bus() {
+    let mut sensor = Sensor::default();
+    sensor.set_temperature(215);
+    let mut chirp = Chirp::new(I2cCompat::new(LegacyBus(sensor)), DEFAULT_ADDRESS);
+    assert_eq!(chirp.temperature_raw(), Ok(215));
+
+    let mut delay = DelayCompat::new(LegacyDelay::default());
+    chirp.address(0x21, &mut delay).unwrap();
+    assert_eq!(delay.destroy().total_us, 1_000_000);
+    assert_eq!(chirp.destroy().destroy().0.address(), 0x21);
+}
+
+#[test]
+fn legacy_bus_error() {
+    let mut chirp = Chirp::new(I2cCompat::new(LegacyBus(Sensor::default())), 0x30);
+    assert_eq!(chirp.version(), Err(Error::I2c(CompatError::I2c(Nack))));
+}
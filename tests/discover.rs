@@ -0,0 +1,72 @@
+mod common;
+
+use chirp::firmware::FirmwareVersion;
+use chirp::sim::{Bus, Sensor};
+use chirp::{discover, discover_in, Device, DEFAULT_ADDRESS, MAX_ADDRESS, MIN_ADDRESS};
+use common::{Mock, Transaction};
+
+fn sensor(address: u8, version: u8) -> Sensor {
+    let mut sensor = Sensor::new(address);
+    sensor.set_version(version);
+    sensor
+}
+
+#[test]
+fn finds_all_chirps() {
+    let mut bus = Bus::new([sensor(0x21, 0x26), sensor(DEFAULT_ADDRESS, 0x23), sensor(0x40, 0x25)]);
+    let found: Vec<Device> = discover(&mut bus).collect();
+    assert_eq!(
+        found,
+        [
+            Device { address: DEFAULT_ADDRESS, firmware: FirmwareVersion::new(2, 3) },
+            Device { address: 0x21, firmware: FirmwareVersion::new(2, 6) },
+            Device { address: 0x40, firmware: FirmwareVersion::new(2, 5) },
+        ]
+    );
+}
+
+#[test]
+fn skips_other_devices() {
+    // a device answering the version register with something that isn't a chirp firmware
+    let mut bus = Bus::new([sensor(0x21, 0x26), sensor(0x50, 0x10), sensor(0x51, 0xff)]);
+    let found: Vec<u8> = discover(&mut bus).map(|device| device.address).collect();
+    assert_eq!(found, [0x21]);
+}
+
+#[test]
+fn does_not_change_sensors() {
+    let mut bus = Bus::new([sensor(0x21, 0x26)]);
+    bus.sensors_mut()[0].set_light(500);
+    assert_eq!(discover(&mut bus).count(), 1);
+    let sensor = bus.sensor(0x21).unwrap();
+    assert!(!sensor.is_busy());
+    assert!(!sensor.is_asleep());
+}
+
+#[test]
+fn custom_range() {
+    let mut bus = Bus::new([sensor(0x03, 0x26), sensor(0x21, 0x26)]);
+    assert_eq!(discover(&mut bus).count(), 1);
+    let found: Vec<u8> = discover_in(&mut bus, 0x01..=0x10).map(|device| device.address).collect();
+    assert_eq!(found, [0x03]);
+}
+
+#[test]
+fn scans_every_valid_address() {
+    let mut bus = Bus::new([sensor(MIN_ADDRESS, 0x26), sensor(MAX_ADDRESS, 0x26)]);
+    let found: Vec<u8> = discover(&mut bus).map(|device| device.address).collect();
+    assert_eq!(found, [MIN_ADDRESS, MAX_ADDRESS]);
+}
+
+#[test]
+fn probes_address_before_reading() {
+    let mut mock = Mock::new(&[
+        Transaction::write(0x21, &[]).with_error(),
+        Transaction::write(0x22, &[]),
+        Transaction::write_read(0x22, &[0x07], &[0x26]),
+        Transaction::write_read(0x22, &[0x02], &[0x22]),
+    ]);
+    let found: Vec<u8> = discover_in(&mut mock, 0x21..=0x22).map(|device| device.address).collect();
+    assert_eq!(found, [0x22]);
+    mock.done();
+}
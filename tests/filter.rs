@@ -0,0 +1,89 @@
+use chirp::filter::{Channel, Filter, FilteredChirp};
+use chirp::sim::Sensor;
+use chirp::units::{Moisture, Temperature};
+use chirp::{Chirp, DEFAULT_ADDRESS};
+
+#[test]
+fn moving_average() {
+    let mut channel: Channel<4> = Channel::new(Filter::MovingAverage);
+    assert_eq!(channel.value(), None);
+    for sample in [10, 20, 30, 40, 50] {
+        channel.push(sample);
+    }
+    assert_eq!(channel.samples().len(), 4);
+    assert_eq!(channel.value(), Some(35));
+}
+
+#[test]
+fn median() {
+    let mut channel: Channel<5> = Channel::new(Filter::Median);
+    for sample in [300, 302, 900, 299, 301] {
+        channel.push(sample);
+    }
+    assert_eq!(channel.value(), Some(301));
+}
+
+#[test]
+fn exponential() {
+    let mut channel: Channel<4> = Channel::new(Filter::Exponential { percent: 50 });
+    channel.push(100);
+    channel.push(200);
+    assert_eq!(channel.value(), Some(150));
+    channel.push(-50);
+    assert_eq!(channel.value(), Some(50));
+}
+
+#[test]
+fn exponential_converges_on_small_steps() {
+    // steps of less than 100 / percent counts used to get lost in the integer division
+    let mut channel: Channel<4> = Channel::new(Filter::Exponential { percent: 10 });
+    channel.push(500);
+    for _ in 0..1000 {
+        channel.push(509);
+    }
+    assert_eq!(channel.value(), Some(509));
+    for _ in 0..1000 {
+        channel.push(-3);
+    }
+    assert_eq!(channel.value(), Some(-3));
+}
+
+#[test]
+fn outliers_discarded() {
+    let mut channel: Channel<3> = Channel::new(Filter::MovingAverage);
+    channel.set_max_deviation(Some(10));
+    assert!(channel.push(300));
+    assert!(channel.push(304));
+    assert!(!channel.push(5000));
+    assert_eq!(channel.value(), Some(302));
+}
+
+#[test]
+fn step_change_accepted_after_n_outliers() {
+    let mut channel: Channel<3> = Channel::new(Filter::Median);
+    channel.set_max_deviation(Some(10));
+    channel.push(300);
+    assert!(!channel.push(500));
+    assert!(!channel.push(500));
+    assert!(channel.push(500));
+    assert_eq!(channel.value(), Some(500));
+}
+
+#[test]
+fn filtered_sensor() {
+    let mut sensor = Sensor::default();
+    sensor.set_moisture(400);
+    sensor.set_temperature(-12);
+    let mut filtered: FilteredChirp<_, 4> = FilteredChirp::new(Chirp::new(sensor, DEFAULT_ADDRESS), Filter::Median);
+    // first read after start up is dropped, the simulated sensor returns 0 there
+    assert_eq!(filtered.capacitance(), Ok(Moisture(400)));
+    assert_eq!(filtered.capacitance(), Ok(Moisture(400)));
+    assert_eq!(filtered.temperature(), Ok(Temperature(-12)));
+    assert_eq!(filtered.capacitance_channel().samples(), &[400, 400]);
+
+    filtered.reset().unwrap();
+    assert!(filtered.capacitance_channel().samples().is_empty());
+    assert_eq!(filtered.capacitance(), Ok(Moisture(400)));
+    assert_eq!(filtered.light_reading().map(|light| light.raw()), Ok(0xffff));
+    assert_eq!(filtered.destroy().get_address(), DEFAULT_ADDRESS);
+}
@@ -0,0 +1,76 @@
+mod common;
+
+use chirp::firmware::{Feature, FirmwareVersion};
+use chirp::sim::Sensor;
+use chirp::units::Moisture;
+use chirp::{Chirp, Error, DEFAULT_ADDRESS};
+use common::{Delay, Mock, Transaction};
+
+#[test]
+fn version_register() {
+    let firmware = FirmwareVersion::from_register(0x26);
+    assert_eq!(firmware, FirmwareVersion::new(2, 6));
+    assert_eq!(firmware.to_register(), 0x26);
+    assert_eq!(format!("{}", firmware), "2.6");
+}
+
+#[test]
+fn ordering() {
+    assert!(FirmwareVersion::new(2, 6) > FirmwareVersion::new(2, 3));
+    assert!(FirmwareVersion::new(3, 0) > FirmwareVersion::new(2, 9));
+    assert!(FirmwareVersion::new(2, 3).supports(Feature::Sleep));
+    assert!(!FirmwareVersion::new(2, 2).supports(Feature::Sleep));
+    assert!(!FirmwareVersion::new(2, 6).supports(Feature::CapacitanceFix));
+}
+
+#[test]
+fn init() {
+    let mock = Mock::new(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26])]);
+    let mut chirp = Chirp::new(mock, DEFAULT_ADDRESS);
+    assert_eq!(chirp.firmware(), None);
+    assert_eq!(chirp.init(), Ok(FirmwareVersion::new(2, 6)));
+    assert_eq!(chirp.firmware(), Some(FirmwareVersion::new(2, 6)));
+    chirp.destroy().done();
+}
+
+#[test]
+fn init_refuses_old_firmware() {
+    let mock = Mock::new(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x21])]);
+    let mut chirp = Chirp::new(mock, DEFAULT_ADDRESS);
+    assert_eq!(chirp.init(), Err(Error::UnsupportedFirmware(FirmwareVersion::new(2, 1))));
+    assert_eq!(chirp.firmware(), None);
+    chirp.destroy().done();
+}
+
+#[test]
+fn sleep_unsupported() {
+    let mock = Mock::new(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x22])]);
+    let mut chirp = Chirp::new(mock, DEFAULT_ADDRESS);
+    chirp.init().unwrap();
+    assert_eq!(chirp.sleep(), Err(Error::UnsupportedFirmware(FirmwareVersion::new(2, 2))));
+    chirp.destroy().done();
+}
+
+#[test]
+fn address_checked_by_version_without_readback_register() {
+    let mut sensor = Sensor::default();
+    sensor.set_version(0x23);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    chirp.init().unwrap();
+    chirp.address(0x24, &mut delay).unwrap();
+    assert_eq!(chirp.get_address(), 0x24);
+}
+
+#[test]
+fn fixed_capacitance_read_once() {
+    let mut sensor = Sensor::default();
+    sensor.set_version(0x27);
+    sensor.set_moisture(512);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    chirp.init().unwrap();
+    assert_eq!(chirp.capacitance(), Ok(Moisture(512)));
+    let reading = chirp.read_all(&mut delay).unwrap();
+    assert_eq!(reading.moisture, Moisture(512));
+}
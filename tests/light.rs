@@ -0,0 +1,45 @@
+use chirp::light::{LightReading, LuxPoint, LuxTable, LuxTableError};
+
+fn table() -> LuxTable {
+    LuxTable::new(&[
+        LuxPoint::new(20, 10_000),
+        LuxPoint::new(200, 1_000),
+        LuxPoint::new(2_000, 100),
+        LuxPoint::new(60_000, 0),
+    ])
+    .unwrap()
+}
+
+#[test]
+fn brighter_means_more_lux() {
+    let table = table();
+    let dark = LightReading::new(50_000);
+    let bright = LightReading::new(100);
+    assert!(bright.lux(&table) > dark.lux(&table));
+    assert!(bright.brightness() > dark.brightness());
+    assert_eq!(bright.raw(), 100);
+}
+
+#[test]
+fn reference_points_and_interpolation() {
+    let table = table();
+    assert_eq!(table.lux(200), 1_000);
+    assert_eq!(table.lux(110), 5_500);
+    assert_eq!(table.lux(1_100), 550);
+}
+
+#[test]
+fn clamped_outside_table() {
+    let table = table();
+    assert_eq!(table.lux(0), 10_000);
+    assert_eq!(table.lux(u16::MAX), 0);
+}
+
+#[test]
+fn invalid_table() {
+    assert_eq!(LuxTable::new(&[LuxPoint::new(20, 10_000)]), Err(LuxTableError::TooFewPoints));
+    assert_eq!(
+        LuxTable::new(&[LuxPoint::new(200, 1_000), LuxPoint::new(20, 10_000)]),
+        Err(LuxTableError::NotIncreasing)
+    );
+}
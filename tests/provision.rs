@@ -0,0 +1,115 @@
+mod common;
+
+use chirp::firmware::FirmwareVersion;
+use chirp::provision::{Assignment, ProvisionError, Provisioner};
+use chirp::sim::{Bus, Sensor};
+use chirp::DEFAULT_ADDRESS;
+use common::{Delay, Mock, Transaction};
+
+#[test]
+fn provisions_sensors_one_by_one() {
+    // second slot is parked outside the pool until that sensor is connected
+    let mut bus = Bus::new([Sensor::default(), Sensor::new(0x70)]);
+    let mut provisioner: Provisioner<4> = Provisioner::new(0x21..=0x30);
+    let mut delay = Delay::default();
+
+    let first = provisioner.provision_next(&mut bus, &mut delay).unwrap();
+    assert_eq!(first, Assignment { index: 0, address: 0x21, firmware: FirmwareVersion::new(2, 6) });
+
+    assert_eq!(provisioner.provision_next(&mut bus, &mut delay), Err(ProvisionError::NoSensor));
+
+    // plug in the next sensor
+    let mut next = Sensor::default();
+    next.set_version(0x25);
+    bus.sensors_mut()[1] = next;
+    let second = provisioner.provision_next(&mut bus, &mut delay).unwrap();
+    assert_eq!(second, Assignment { index: 1, address: 0x22, firmware: FirmwareVersion::new(2, 5) });
+
+    assert_eq!(provisioner.assignments(), &[first, second]);
+    assert!(bus.sensor(0x21).is_some());
+    assert!(bus.sensor(0x22).is_some());
+    assert!(bus.sensor(DEFAULT_ADDRESS).is_none());
+}
+
+#[test]
+fn skips_taken_addresses() {
+    let mut bus = Bus::new([Sensor::default(), Sensor::new(0x21)]);
+    let mut provisioner: Provisioner<2> = Provisioner::new(0x20..=0x30);
+    let mut delay = Delay::default();
+    let assignment = provisioner.provision_next(&mut bus, &mut delay).unwrap();
+    assert_eq!(assignment.address, 0x22);
+}
+
+#[test]
+fn pool_exhausted() {
+    let mut bus = Bus::new([Sensor::default(), Sensor::new(0x21)]);
+    let mut provisioner: Provisioner<2> = Provisioner::new(0x21..=0x21);
+    let mut delay = Delay::default();
+    assert_eq!(provisioner.provision_next(&mut bus, &mut delay), Err(ProvisionError::PoolExhausted));
+    assert_eq!(bus.sensors()[0].address(), DEFAULT_ADDRESS);
+}
+
+#[test]
+fn mapping_full() {
+    let mut bus = Bus::new([Sensor::default()]);
+    let mut provisioner: Provisioner<0> = Provisioner::new(0x21..=0x30);
+    let mut delay = Delay::default();
+    assert_eq!(provisioner.provision_next(&mut bus, &mut delay), Err(ProvisionError::Full));
+}
+
+#[test]
+fn device_left_at_default_address() {
+    let mut mock = Mock::new(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        Transaction::write_read(0x21, &[0x07], &[0x00]).with_error(),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x02], &[0x20]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x01, 0x21]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x01, 0x21]),
+        Transaction::write(DEFAULT_ADDRESS, &[0x06]),
+        Transaction::write_read(0x21, &[0x09], &[0x00]),
+        Transaction::write_read(0x21, &[0x02], &[0x21]),
+        Transaction::write_read(0x21, &[0x07], &[0x26]),
+        // still something answering at the default address
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        // moved back
+        Transaction::write(0x21, &[0x01, DEFAULT_ADDRESS]),
+        Transaction::write(0x21, &[0x01, DEFAULT_ADDRESS]),
+        Transaction::write(0x21, &[0x06]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x02], &[DEFAULT_ADDRESS]),
+    ]);
+    let mut provisioner: Provisioner<2> = Provisioner::new(0x21..=0x30);
+    let mut delay = Delay::default();
+    assert_eq!(provisioner.provision_next(&mut mock, &mut delay), Err(ProvisionError::MultipleSensors));
+    assert!(provisioner.assignments().is_empty());
+    mock.done();
+}
+
+#[test]
+fn several_sensors_at_default_address() {
+    // the second device pulls bits of the get address answer low
+    let mut mock = Mock::new(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        Transaction::write_read(0x21, &[0x07], &[0x00]).with_error(),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x07], &[0x26]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x02], &[0x00]),
+    ]);
+    let mut provisioner: Provisioner<2> = Provisioner::new(0x21..=0x30);
+    let mut delay = Delay::default();
+    assert_eq!(provisioner.provision_next(&mut mock, &mut delay), Err(ProvisionError::MultipleSensors));
+    assert!(provisioner.assignments().is_empty());
+    mock.done();
+}
+
+#[test]
+fn implausible_version_at_default_address() {
+    // two devices answering 0xff and 0xfe, no chirp firmware
+    let mut bus = Bus::new([Sensor::default(), Sensor::default()]);
+    bus.sensors_mut()[0].set_version(0xff);
+    bus.sensors_mut()[1].set_version(0xfe);
+    let mut provisioner: Provisioner<2> = Provisioner::new(0x21..=0x30);
+    let mut delay = Delay::default();
+    assert_eq!(provisioner.provision_next(&mut bus, &mut delay), Err(ProvisionError::MultipleSensors));
+    assert!(bus.sensors().iter().all(|sensor| sensor.address() == DEFAULT_ADDRESS));
+}
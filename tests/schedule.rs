@@ -0,0 +1,211 @@
+mod common;
+
+use std::cell::Cell;
+
+use chirp::light::LightReading;
+use chirp::schedule::{Channel, Sample, Scheduler, Value};
+use chirp::sim::{Nack, Sensor};
+use chirp::units::{Moisture, Temperature};
+use chirp::{Backoff, Chirp, Error, CAPACITANCE_SETTLE_MS, DEFAULT_ADDRESS, MEASUREMENT_TIMEOUT_MS};
+use common::{Mock, Transaction};
+use embedded_hal::delay::DelayNs;
+
+// delay that moves the clock of the scheduler along
+struct Time<'a>(&'a Cell<u64>);
+
+impl DelayNs for Time<'_> {
+    fn delay_ns(&mut self, ns: u32) {
+        self.0.set(self.0.get() + (ns / 1_000_000) as u64);
+    }
+
+    fn delay_ms(&mut self, ms: u32) {
+        self.0.set(self.0.get() + ms as u64);
+    }
+}
+
+fn sensor() -> Sensor {
+    let mut sensor = Sensor::default();
+    sensor.set_version(0x27);
+    sensor.set_moisture(350);
+    sensor.set_temperature(215);
+    sensor.set_light(1200);
+    sensor
+}
+
+fn chirp(sensor: Sensor) -> Chirp<Sensor> {
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    chirp.init().unwrap();
+    chirp
+}
+
+#[test]
+fn nothing_scheduled() {
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp(sensor()), || now.get());
+    assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    assert_eq!(scheduler.next_event_ms(), None);
+}
+
+#[test]
+fn channels_at_their_own_interval() {
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp(sensor()), || now.get());
+    scheduler.set_interval(Channel::Moisture, Some(60_000));
+    scheduler.set_interval(Channel::Light, Some(900_000));
+    let mut time = Time(&now);
+    let samples: Vec<Sample> = scheduler.samples(&mut time).take(18).map(Result::unwrap).collect();
+
+    let moisture: Vec<u64> = samples
+        .iter()
+        .filter(|sample| matches!(sample.value, Value::Moisture(_)))
+        .map(|sample| sample.timestamp_ms)
+        .collect();
+    assert_eq!(moisture, (0..16).map(|minute| minute * 60_000).collect::<Vec<u64>>());
+    let light: Vec<&Sample> = samples.iter().filter(|sample| matches!(sample.value, Value::Light(_))).collect();
+    assert_eq!(light.len(), 2);
+    assert_eq!(light[0].value, Value::Light(LightReading::new(1200)));
+    assert!(light[1].timestamp_ms >= 900_000 && light[1].timestamp_ms < 901_000);
+    assert!(!samples.iter().any(|sample| matches!(sample.value, Value::Temperature(_))));
+}
+
+#[test]
+fn temperature_and_moisture_values() {
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp(sensor()), || now.get());
+    scheduler.set_interval(Channel::Moisture, Some(1000));
+    scheduler.set_interval(Channel::Temperature, Some(1000));
+    assert_eq!(scheduler.poll(), Ok(Sample { timestamp_ms: 0, value: Value::Moisture(Moisture(350)) }));
+    assert_eq!(scheduler.poll(), Ok(Sample { timestamp_ms: 0, value: Value::Temperature(Temperature(215)) }));
+    assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    assert_eq!(scheduler.next_event_ms(), Some(1000));
+}
+
+#[test]
+fn old_firmware_reads_capacitance_twice() {
+    let mut sensor = sensor();
+    sensor.set_version(0x26);
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp(sensor), || now.get());
+    scheduler.set_interval(Channel::Moisture, Some(1000));
+    assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    assert_eq!(scheduler.next_event_ms(), Some(CAPACITANCE_SETTLE_MS as u64));
+    now.set(CAPACITANCE_SETTLE_MS as u64);
+    assert_eq!(
+        scheduler.poll(),
+        Ok(Sample { timestamp_ms: CAPACITANCE_SETTLE_MS as u64, value: Value::Moisture(Moisture(350)) })
+    );
+}
+
+#[test]
+fn sleeps_between_samples() {
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp(sensor()), || now.get());
+    scheduler.set_interval(Channel::Temperature, Some(1000));
+    scheduler.set_sleep(true).unwrap();
+    assert!(scheduler.poll().is_ok());
+    assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    let (chirp, _) = scheduler.destroy();
+    assert!(chirp.destroy().is_asleep());
+}
+
+#[test]
+fn wakes_for_the_next_sample() {
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp(sensor()), || now.get());
+    scheduler.set_interval(Channel::Temperature, Some(1000));
+    scheduler.set_sleep(true).unwrap();
+    let mut time = Time(&now);
+    let samples: Vec<u64> = scheduler.samples(&mut time).take(3).map(|sample| sample.unwrap().timestamp_ms).collect();
+    assert_eq!(samples[0], 0);
+    // woken up when due, read once the sensor answers again
+    assert!(samples[1] > 1000 && samples[1] < 1100);
+    assert!(samples[2] > 2000 && samples[2] < 2100);
+}
+
+#[test]
+fn sleep_needs_firmware_support() {
+    let mut sensor = sensor();
+    sensor.set_version(0x22);
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp(sensor), || now.get());
+    assert!(matches!(scheduler.set_sleep(true), Err(Error::UnsupportedFirmware(_))));
+    assert_eq!(scheduler.set_sleep(false), Ok(()));
+}
+
+#[test]
+fn failed_sleep_is_not_retried_every_poll() {
+    // firmware not probed with init(), the sensor doesn't acknowledge the sleep command
+    let mut sensor = sensor();
+    sensor.set_version(0x22);
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(Chirp::new(sensor, DEFAULT_ADDRESS), || now.get());
+    scheduler.set_interval(Channel::Temperature, Some(1000));
+    scheduler.set_sleep(true).unwrap();
+    assert!(scheduler.poll().is_ok());
+    assert_eq!(scheduler.poll(), Err(nb::Error::Other(Error::I2c(Nack))));
+    assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    now.set(1000);
+    assert!(scheduler.poll().is_ok());
+}
+
+#[test]
+fn light_polled_with_backoff() {
+    let mut sensor = sensor();
+    sensor.set_measurement_transactions(u8::MAX);
+    let mut chirp = chirp(sensor);
+    chirp.set_backoff(Backoff::exponential(10, 1000));
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp, || now.get());
+    scheduler.set_interval(Channel::Light, Some(60_000));
+    assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    let mut checks = Vec::new();
+    for _ in 0..4 {
+        let next = scheduler.next_event_ms().unwrap();
+        checks.push(next);
+        now.set(next);
+        assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    }
+    assert_eq!(checks, [10, 30, 70, 150]);
+}
+
+#[test]
+fn busy_checked_only_when_due() {
+    let mut chirp = Chirp::new(
+        Mock::new(&[
+            Transaction::write(DEFAULT_ADDRESS, &[0x03]),
+            Transaction::write_read(DEFAULT_ADDRESS, &[0x09], &[0x00]),
+            Transaction::write_read(DEFAULT_ADDRESS, &[0x04], &[0x04, 0xb0]),
+        ]),
+        DEFAULT_ADDRESS,
+    );
+    chirp.set_backoff(Backoff::fixed(500));
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp, || now.get());
+    scheduler.set_interval(Channel::Light, Some(60_000));
+    for _ in 0..50 {
+        assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    }
+    now.set(499);
+    assert_eq!(scheduler.poll(), Err(nb::Error::WouldBlock));
+    now.set(500);
+    assert_eq!(
+        scheduler.poll(),
+        Ok(Sample { timestamp_ms: 500, value: Value::Light(LightReading::new(1200)) })
+    );
+    let (chirp, _) = scheduler.destroy();
+    chirp.destroy().done();
+}
+
+#[test]
+fn light_timeout() {
+    let mut sensor = sensor();
+    sensor.set_measurement_transactions(u8::MAX);
+    let now = Cell::new(0);
+    let mut scheduler = Scheduler::new(chirp(sensor), || now.get());
+    scheduler.set_interval(Channel::Light, Some(60_000));
+    let mut time = Time(&now);
+    assert_eq!(scheduler.next_sample(&mut time), Err(Error::BusyTimeout));
+    assert!(now.get() >= MEASUREMENT_TIMEOUT_MS as u64);
+    assert_eq!(scheduler.next_event_ms(), Some(60_000));
+}
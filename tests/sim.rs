@@ -0,0 +1,159 @@
+mod common;
+
+use chirp::light::LightReading;
+use chirp::sim::{Bus, Nack, Sensor};
+use chirp::units::{Moisture, Temperature};
+use chirp::{Chirp, Error, DEFAULT_ADDRESS, MEASUREMENT_TIMEOUT_MS};
+use common::Delay;
+
+#[test]
+fn readings() {
+    let mut sensor = Sensor::default();
+    sensor.set_moisture(420);
+    sensor.set_temperature(-35);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    assert_eq!(chirp.version(), Ok(0x26));
+    assert_eq!(chirp.temperature_raw(), Ok(-35));
+    // capacitance returns the result of the previous read
+    assert_eq!(chirp.capacitance(), Ok(Moisture(0)));
+    assert_eq!(chirp.capacitance(), Ok(Moisture(420)));
+    assert_eq!(chirp.capacitance(), Ok(Moisture(420)));
+}
+
+#[test]
+fn light_measurement_keeps_sensor_busy() {
+    let mut sensor = Sensor::default();
+    sensor.set_light(1200);
+    sensor.set_measurement_transactions(2);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    let previous = chirp.light().unwrap();
+    chirp.messure().unwrap();
+    assert_eq!(chirp.busy(), Ok(true));
+    assert_eq!(chirp.busy(), Ok(false));
+    assert_ne!(chirp.light().unwrap(), previous);
+    assert_eq!(chirp.light(), Ok(LightReading::new(1200)));
+}
+
+#[test]
+fn light_measurement_never_stale() {
+    let mut sensor = Sensor::default();
+    sensor.set_light(1200);
+    sensor.set_measurement_transactions(4);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    let measurement = chirp.start_measurement().unwrap();
+    let mut polls = 0;
+    let light = loop {
+        match chirp.read_light(&measurement) {
+            Err(nb::Error::WouldBlock) => polls += 1,
+            result => break result,
+        }
+    };
+    assert_eq!(light.map(|light| light.raw()), Ok(1200));
+    assert_eq!(polls, 3);
+}
+
+#[test]
+fn early_light_read_returns_previous_result() {
+    let mut sensor = Sensor::default();
+    sensor.set_light(1200);
+    sensor.set_measurement_transactions(5);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    chirp.messure().unwrap();
+    assert_eq!(chirp.light(), Ok(LightReading::new(u16::MAX)));
+}
+
+#[test]
+fn address_taken_after_reset() {
+    let mut chirp = Chirp::new(Sensor::default(), DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    chirp.address(0x30, &mut delay).unwrap();
+    assert_eq!(chirp.get_address(), 0x30);
+    assert_eq!(chirp.destroy().address(), 0x30);
+}
+
+#[test]
+fn address_change_of_old_firmware_without_init() {
+    // no get address register before 2.4, the new address is checked through the version
+    let mut sensor = Sensor::default();
+    sensor.set_version(0x23);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    chirp.address(0x21, &mut delay).unwrap();
+    assert_eq!(chirp.get_address(), 0x21);
+    assert_eq!(chirp.destroy().address(), 0x21);
+}
+
+#[test]
+fn single_address_write_is_ignored() {
+    use embedded_hal::i2c::I2c;
+
+    let mut sensor = Sensor::default();
+    sensor.write(DEFAULT_ADDRESS, &[0x01, 0x30]).unwrap();
+    sensor.write(DEFAULT_ADDRESS, &[0x06]).unwrap();
+    assert_eq!(sensor.address(), DEFAULT_ADDRESS);
+}
+
+#[test]
+fn sleeping_sensor_wakes_on_transaction() {
+    let mut chirp = Chirp::new(Sensor::default(), DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    chirp.sleep().unwrap();
+    assert_eq!(chirp.version(), Err(Error::I2c(Nack)));
+    assert_eq!(chirp.version(), Ok(0x26));
+    chirp.sleep().unwrap();
+    chirp.wake(&mut delay).unwrap();
+}
+
+#[test]
+fn multiple_sensors_on_one_bus() {
+    let mut first = Sensor::new(0x21);
+    first.set_temperature(200);
+    let mut second = Sensor::new(0x22);
+    second.set_temperature(-50);
+    let bus = Bus::new([first, second]);
+
+    let mut chirp = Chirp::new(bus, 0x21);
+    assert_eq!(chirp.temperature_raw(), Ok(200));
+    let mut bus = chirp.destroy();
+    assert!(bus.sensor(0x23).is_none());
+    bus.sensor_mut(0x22).unwrap().set_temperature(-60);
+
+    let mut chirp = Chirp::new(bus, 0x22);
+    assert_eq!(chirp.temperature_raw(), Ok(-60));
+    let mut chirp = Chirp::new(chirp.destroy(), 0x23);
+    assert_eq!(chirp.version(), Err(Error::I2c(Nack)));
+}
+
+#[test]
+fn sensors_sharing_an_address() {
+    let mut chirp = Chirp::new(Bus::new([Sensor::default(), Sensor::default()]), DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    chirp.address(0x30, &mut delay).unwrap();
+    let bus = chirp.destroy();
+    assert!(bus.sensors().iter().all(|sensor| sensor.address() == 0x30));
+}
+
+#[test]
+fn read_all() {
+    let mut sensor = Sensor::default();
+    sensor.set_moisture(480);
+    sensor.set_temperature(-20);
+    sensor.set_light(900);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    let reading = chirp.read_all(&mut delay).unwrap();
+    assert_eq!(reading.moisture, Moisture(480));
+    assert_eq!(reading.temperature, Temperature(-20));
+    assert_eq!(reading.light.raw(), 900);
+    assert_eq!(reading.timestamp_hint, delay.total_ms);
+}
+
+#[test]
+fn read_all_times_out() {
+    let mut sensor = Sensor::default();
+    sensor.set_measurement_transactions(u8::MAX);
+    let mut chirp = Chirp::new(sensor, DEFAULT_ADDRESS);
+    let mut delay = Delay::default();
+    assert_eq!(chirp.read_all(&mut delay), Err(Error::BusyTimeout));
+    assert!(delay.total_ms >= MEASUREMENT_TIMEOUT_MS);
+}
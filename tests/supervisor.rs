@@ -0,0 +1,110 @@
+mod common;
+
+use std::cell::RefCell;
+
+use chirp::sim::{Nack, Sensor};
+use chirp::supervisor::{Fault, Limits, Status, Supervisor};
+use chirp::{Chirp, Error, DEFAULT_ADDRESS};
+use common::Delay;
+use embedded_hal::i2c::{ErrorType, I2c, Operation};
+
+// lets the test change the sensor while the supervisor owns the driver
+struct Shared<'a>(&'a RefCell<Sensor>);
+
+impl ErrorType for Shared<'_> {
+    type Error = Nack;
+}
+
+impl I2c for Shared<'_> {
+    fn transaction(&mut self, address: u8, operations: &mut [Operation<'_>]) -> Result<(), Nack> {
+        self.0.borrow_mut().transaction(address, operations)
+    }
+}
+
+fn supervisor(sensor: &RefCell<Sensor>) -> Supervisor<Shared<'_>> {
+    Supervisor::new(Chirp::new(Shared(sensor), DEFAULT_ADDRESS))
+}
+
+#[test]
+fn good_readings() {
+    let sensor = RefCell::new(Sensor::default());
+    sensor.borrow_mut().set_moisture(400);
+    let mut supervisor = supervisor(&sensor);
+    let mut delay = Delay::default();
+    for moisture in 401..405 {
+        assert!(matches!(supervisor.poll(&mut delay), Status::Reading(_)));
+        sensor.borrow_mut().set_moisture(moisture);
+    }
+    let stats = supervisor.stats();
+    assert_eq!((stats.polls, stats.readings, stats.recoveries), (4, 4, 0));
+}
+
+#[test]
+fn stuck_sensor_is_restarted() {
+    let sensor = RefCell::new(Sensor::default());
+    sensor.borrow_mut().set_moisture(400);
+    let mut supervisor = supervisor(&sensor);
+    supervisor.set_stuck_cycles(Some(3));
+    let mut delay = Delay::default();
+    assert!(matches!(supervisor.poll(&mut delay), Status::Reading(_)));
+    assert!(matches!(supervisor.poll(&mut delay), Status::Reading(_)));
+    assert_eq!(supervisor.poll(&mut delay), Status::Recovered(Fault::Stuck));
+    // counting starts over after the restart
+    assert!(matches!(supervisor.poll(&mut delay), Status::Reading(_)));
+    let stats = supervisor.stats();
+    assert_eq!((stats.stuck, stats.recoveries, stats.readings), (1, 1, 3));
+}
+
+#[test]
+fn implausible_values() {
+    let sensor = RefCell::new(Sensor::default());
+    sensor.borrow_mut().set_moisture(400);
+    sensor.borrow_mut().set_temperature(900);
+    let mut supervisor = supervisor(&sensor);
+    let mut delay = Delay::default();
+    assert!(matches!(supervisor.poll(&mut delay), Status::Recovered(Fault::Implausible(reading)) if reading.temperature.deci_celsius() == 900));
+
+    supervisor.set_limits(Limits { temperature: -400..=1000, ..Limits::default() });
+    assert!(matches!(supervisor.poll(&mut delay), Status::Reading(_)));
+    assert_eq!(supervisor.stats().implausible, 1);
+}
+
+#[test]
+fn nack_recovers() {
+    let sensor = RefCell::new(Sensor::default());
+    sensor.borrow_mut().set_moisture(400);
+    let mut supervisor = supervisor(&sensor);
+    let mut delay = Delay::default();
+    supervisor.chirp().sleep().unwrap();
+    assert_eq!(supervisor.poll(&mut delay), Status::Recovered(Fault::Bus(Error::I2c(Nack))));
+    assert!(matches!(supervisor.poll(&mut delay), Status::Reading(_)));
+    assert_eq!(supervisor.stats().bus_errors, 1);
+}
+
+#[test]
+fn dead_sensor_keeps_failing() {
+    let sensor = RefCell::new(Sensor::new(0x21));
+    let mut supervisor = supervisor(&sensor);
+    let mut delay = Delay::default();
+    for _ in 0..3 {
+        assert_eq!(supervisor.poll(&mut delay), Status::Failed(Fault::Bus(Error::I2c(Nack)), Error::I2c(Nack)));
+    }
+    let stats = supervisor.stats();
+    assert_eq!((stats.bus_errors, stats.failed_recoveries, stats.consecutive_failures), (3, 3, 3));
+}
+
+#[test]
+fn version_changed_after_restart() {
+    let sensor = RefCell::new(Sensor::default());
+    sensor.borrow_mut().set_moisture(400);
+    let mut supervisor = supervisor(&sensor);
+    supervisor.set_stuck_cycles(Some(2));
+    let mut delay = Delay::default();
+    assert!(matches!(supervisor.poll(&mut delay), Status::Reading(_)));
+    sensor.borrow_mut().set_version(0x27);
+    assert_eq!(
+        supervisor.poll(&mut delay),
+        Status::Failed(Fault::Stuck, Error::UnexpectedValue { register: 0x07, expected: 0x26, found: 0x27 })
+    );
+    assert_eq!(supervisor.stats().consecutive_failures, 1);
+}
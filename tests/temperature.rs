@@ -0,0 +1,116 @@
+mod common;
+
+use chirp::units::Temperature;
+use chirp::{Chirp, DEFAULT_ADDRESS};
+use common::{Mock, Transaction};
+
+fn temperature_raw(response: [u8; 2]) -> i16 {
+    let mock = Mock::new(&[Transaction::write_read(DEFAULT_ADDRESS, &[0x05], &response)]);
+    let mut chirp = Chirp::new(mock, DEFAULT_ADDRESS);
+    let value = chirp.temperature_raw().unwrap();
+    chirp.destroy().done();
+    value
+}
+
+#[test]
+fn positive_temperature() {
+    assert_eq!(temperature_raw([0x00, 0xeb]), 235);
+    assert_eq!(temperature_raw([0x00, 0x01]), 1);
+}
+
+#[test]
+fn zero_temperature() {
+    assert_eq!(temperature_raw([0x00, 0x00]), 0);
+}
+
+#[test]
+fn negative_temperature() {
+    assert_eq!(temperature_raw([0xff, 0xff]), -1);
+    assert_eq!(temperature_raw([0xff, 0xf1]), -15);
+    assert_eq!(temperature_raw([0xff, 0x38]), -200);
+}
+
+#[test]
+fn extreme_temperature() {
+    assert_eq!(temperature_raw([0x7f, 0xff]), i16::MAX);
+    assert_eq!(temperature_raw([0x80, 0x00]), i16::MIN);
+}
+
+#[test]
+#[cfg(feature = "float")]
+fn temperature_in_celsius() {
+    let mock = Mock::new(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x05], &[0xff, 0xf1]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x05], &[0x00, 0xeb]),
+    ]);
+    let mut chirp = Chirp::new(mock, DEFAULT_ADDRESS);
+    assert_eq!(chirp.temperature().unwrap().to_f32(), -1.5);
+    assert_eq!(chirp.temperature().unwrap().to_f32(), 23.5);
+    chirp.destroy().done();
+}
+
+#[test]
+fn fixed_point_temperature() {
+    let mock = Mock::new(&[
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x05], &[0xff, 0xf1]),
+        Transaction::write_read(DEFAULT_ADDRESS, &[0x05], &[0x00, 0xeb]),
+    ]);
+    let mut chirp = Chirp::new(mock, DEFAULT_ADDRESS);
+    let temperature = chirp.temperature().unwrap();
+    assert_eq!(temperature, Temperature(-15));
+    assert_eq!(temperature.deci_celsius(), -15);
+    assert_eq!(temperature.celsius(), -2);
+    assert_eq!(chirp.temperature().unwrap().celsius(), 24);
+    chirp.destroy().done();
+}
+
+#[test]
+fn whole_degrees_round_half_away_from_zero() {
+    assert_eq!(Temperature(14).celsius(), 1);
+    assert_eq!(Temperature(15).celsius(), 2);
+    assert_eq!(Temperature(-14).celsius(), -1);
+    assert_eq!(Temperature(i16::MAX).celsius(), 3277);
+    assert_eq!(Temperature(i16::MIN).celsius(), -3277);
+}
+
+#[test]
+#[cfg(feature = "float")]
+fn lazy_float_conversion() {
+    assert_eq!(Temperature(-15).to_f32(), -1.5);
+    assert_eq!(chirp::light::LightReading::new(1200).to_f32(), 120.0);
+}
+
+#[test]
+fn fahrenheit_and_kelvin() {
+    assert_eq!(Temperature(0).deci_fahrenheit(), 320);
+    assert_eq!(Temperature(1000).deci_fahrenheit(), 2120);
+    assert_eq!(Temperature(-400).deci_fahrenheit(), -400);
+    assert_eq!(Temperature(235).deci_fahrenheit(), 743);
+    assert_eq!(Temperature(0).centi_kelvin(), 27315);
+    assert_eq!(Temperature(-15).centi_kelvin(), 27165);
+}
+
+#[test]
+#[cfg(feature = "float")]
+fn fahrenheit_and_kelvin_as_float() {
+    assert_eq!(Temperature(1000).to_fahrenheit_f32(), 212.0);
+    assert_eq!(Temperature(0).to_kelvin_f32(), 273.15);
+}
+
+#[test]
+fn display_temperature() {
+    assert_eq!(Temperature(235).to_string(), "23.5 °C");
+    assert_eq!(Temperature(-15).to_string(), "-1.5 °C");
+    assert_eq!(Temperature(-5).to_string(), "-0.5 °C");
+    assert_eq!(Temperature(i16::MIN).to_string(), "-3276.8 °C");
+}
+
+#[test]
+#[cfg(feature = "uom")]
+fn uom_temperature() {
+    use uom::si::f32::ThermodynamicTemperature;
+    use uom::si::thermodynamic_temperature::kelvin;
+
+    let temperature: ThermodynamicTemperature = Temperature(0).into();
+    assert_eq!(temperature.get::<kelvin>(), 273.15);
+}
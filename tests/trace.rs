@@ -0,0 +1,100 @@
+#![cfg(feature = "log")]
+
+mod common;
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Once;
+
+use chirp::{Chirp, Error, DEFAULT_ADDRESS};
+use common::{Mock, MockError, Transaction};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+// records of the current test thread, tests run in parallel
+thread_local! {
+    static RECORDS: RefCell<Vec<(Level, String)>> = const { RefCell::new(Vec::new()) };
+}
+
+struct Capture;
+
+impl Log for Capture {
+    fn enabled(&self, _: &Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &Record) {
+        RECORDS.with(|records| records.borrow_mut().push((record.level(), record.args().to_string())));
+    }
+
+    fn flush(&self) {}
+}
+
+static CAPTURE: Capture = Capture;
+static INIT: Once = Once::new();
+
+fn capture() {
+    INIT.call_once(|| {
+        log::set_logger(&CAPTURE).unwrap();
+        log::set_max_level(LevelFilter::Trace);
+    });
+    RECORDS.with(|records| records.borrow_mut().clear());
+}
+
+fn records() -> Vec<(Level, String)> {
+    RECORDS.with(|records| records.borrow().clone())
+}
+
+// every call is 150 microseconds after the previous one
+fn clock() -> u64 {
+    static NOW: AtomicU64 = AtomicU64::new(0);
+    NOW.fetch_add(150, Ordering::Relaxed)
+}
+
+#[test]
+fn traces_register_accesses() {
+    capture();
+    let mut chirp = Chirp::new(
+        Mock::new(&[
+            Transaction::write_read(DEFAULT_ADDRESS, &[0x05], &[0x00, 0xeb]),
+            Transaction::write(DEFAULT_ADDRESS, &[0x06]).with_error(),
+        ]),
+        DEFAULT_ADDRESS,
+    );
+    assert_eq!(chirp.temperature_raw(), Ok(235));
+    assert_eq!(chirp.reset(), Err(Error::I2c(MockError)));
+    assert_eq!(
+        records(),
+        [
+            (Level::Trace, "chirp 0x20 temperature sent [05]".to_string()),
+            (Level::Trace, "chirp 0x20 temperature received [00, eb]".to_string()),
+            (Level::Trace, "chirp 0x20 reset sent [06]".to_string()),
+            (Level::Warn, "chirp 0x20 reset failed: MockError".to_string()),
+        ]
+    );
+    chirp.destroy().done();
+}
+
+#[test]
+fn traces_duration_with_clock() {
+    capture();
+    let mut chirp = Chirp::new(
+        Mock::new(&[
+            Transaction::write_read(0x21, &[0x07], &[0x26]),
+            Transaction::write_read(0x21, &[0x07], &[0x26]).with_error(),
+        ]),
+        0x21,
+    );
+    chirp.set_trace_clock(clock);
+    assert_eq!(chirp.version(), Ok(0x26));
+    assert!(chirp.version().is_err());
+    assert_eq!(
+        records(),
+        [
+            (Level::Trace, "chirp 0x21 version sent [07]".to_string()),
+            (Level::Trace, "chirp 0x21 version received [26] in 150 us".to_string()),
+            (Level::Trace, "chirp 0x21 version sent [07]".to_string()),
+            (Level::Warn, "chirp 0x21 version failed: MockError in 150 us".to_string()),
+        ]
+    );
+    chirp.destroy().done();
+}